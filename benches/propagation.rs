@@ -0,0 +1,70 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use small_sat::solver::Solver;
+
+/// Formulas propagated by each benchmark, from small to large
+const FIXTURES: [(&str, &str); 4] = [
+  ("aim-50 (sat)", "src/bin/data/small/aim-50-1_6-yes.cnf"),
+  ("aim-100 (unsat)", "src/bin/data/small/aim-100-1_6-no.cnf"),
+  ("pret150 (unsat)", "src/bin/data/med_unsat/pret150_25.cnf"),
+  ("bmc-ibm-1 (sat)", "src/bin/data/bmc/bmc-ibm-1.cnf"),
+];
+
+/// Decides and propagates until the first conflict or until every variable is assigned,
+/// returning the number of decisions made.
+fn to_first_conflict(solver: &mut Solver) -> usize {
+  let mut decisions = 0;
+  while solver.decide().is_some() {
+    decisions += 1;
+    if solver.propagate().is_some() {
+      break;
+    }
+  }
+  decisions
+}
+
+/// Searches until the given number of conflicts, so that propagation after backjumping and
+/// over learnt clauses is measured as well.
+fn to_conflicts(solver: &mut Solver, conflicts: usize) {
+  let mut seen = 0;
+  while seen < conflicts && solver.decide().is_some() {
+    while let Some(conflict) = solver.propagate() {
+      seen += 1;
+      if !solver.analyze_and_backtrack(&conflict) {
+        return;
+      }
+    }
+  }
+}
+
+fn first_conflict(c: &mut Criterion) {
+  let mut group = c.benchmark_group("first_conflict");
+  for &(name, path) in FIXTURES.iter() {
+    let solver = Solver::from_dimacs(path).expect("missing benchmark fixture");
+    group.bench_function(name, |b| {
+      b.iter_batched(
+        || solver.clone(),
+        |mut solver| to_first_conflict(&mut solver),
+        BatchSize::SmallInput,
+      )
+    });
+  }
+  group.finish();
+}
+
+fn hundred_conflicts(c: &mut Criterion) {
+  let mut group = c.benchmark_group("100_conflicts");
+  for &(name, path) in FIXTURES.iter() {
+    let solver = Solver::from_dimacs(path).expect("missing benchmark fixture");
+    group.bench_function(name, |b| {
+      b.iter_batched(
+        || solver.clone(),
+        |mut solver| to_conflicts(&mut solver, 100),
+        BatchSize::SmallInput,
+      )
+    });
+  }
+  group.finish();
+}
+
+criterion_group!(benches, first_conflict, hundred_conflicts);
+criterion_main!(benches);
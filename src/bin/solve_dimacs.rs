@@ -1,58 +1,254 @@
 extern crate core_affinity;
 
-use small_sat::solver::Solver;
-use std::{env, thread, time::Duration};
+use small_sat::{
+  builder::SolverBuilder,
+  database::ClauseDatabase,
+  dimacs::{from_icnf, write_model, IcnfLine},
+  literal::Literal,
+  solver::{SolveResult, Solver},
+};
+use std::{
+  env,
+  io::{self, Write},
+  process, thread,
+  time::{Duration, Instant},
+};
+
+/// Flags passed to this binary, any argument not starting with `--` is a file to solve.
+#[derive(Debug, Clone)]
+struct Options {
+  /// print a heartbeat to stderr while solving
+  progress: bool,
+  /// print results in the SAT competition format
+  competition: bool,
+  /// number of solvers to run in parallel
+  threads: usize,
+  /// whether to pin each solver's thread to a core
+  affinity: bool,
+  /// how long to search before giving up with an unknown result
+  timeout: Option<Duration>,
+  /// conflicts between clause exchanges if solvers should run deterministically
+  deterministic: Option<u64>,
+  /// whether solvers share learnt clauses, or search independently
+  share_clauses: bool,
+  /// DIMACS literals assumed while solving
+  assumptions: Vec<i32>,
+  /// whether files are in the incremental iCNF format, with a result printed per query
+  icnf: bool,
+}
+
+impl Options {
+  fn from_args() -> Self {
+    let mut opts = Self {
+      progress: false,
+      competition: false,
+      threads: thread::available_parallelism().map_or(1, |n| n.get()),
+      affinity: true,
+      timeout: None,
+      deterministic: None,
+      share_clauses: true,
+      assumptions: vec![],
+      icnf: false,
+    };
+    for arg in env::args().skip(1).filter(|v| v.starts_with("--")) {
+      match arg.as_str() {
+        "--progress" => opts.progress = true,
+        "--competition" => opts.competition = true,
+        "--no-affinity" => opts.affinity = false,
+        "--no-sharing" => opts.share_clauses = false,
+        "--icnf" => opts.icnf = true,
+        _ => {
+          if let Some(n) = arg.strip_prefix("--threads=") {
+            opts.threads = n.parse().expect("--threads expects a number");
+          } else if let Some(secs) = arg.strip_prefix("--timeout=") {
+            let secs = secs.parse().expect("--timeout expects a number of seconds");
+            opts.timeout = Some(Duration::from_secs_f64(secs));
+          } else if let Some(k) = arg.strip_prefix("--deterministic=") {
+            let k = k
+              .parse()
+              .expect("--deterministic expects a number of conflicts");
+            opts.deterministic = Some(k);
+          } else if let Some(lits) = arg.strip_prefix("--assume=") {
+            opts.assumptions = lits
+              .split(',')
+              .map(|lit| match lit.trim().parse() {
+                Ok(0) | Err(_) => panic!("--assume expects nonzero DIMACS literals"),
+                Ok(lit) => lit,
+              })
+              .collect();
+          } else {
+            eprintln!("c ignoring unknown flag {}", arg);
+          }
+        },
+      }
+    }
+    opts.threads = opts.threads.max(1);
+    opts
+  }
+}
 
 fn main() {
-  // specify how many cores to run this on
   // let start = std::time::Instant::now();
+  let opts = Options::from_args();
+  let mut result = None;
   for arg in env::args().skip(1).filter(|v| !v.starts_with("--")) {
     // println!("Starting {:?}", arg);
-    // multi_threaded(&arg, 4);
-    single_threaded(&arg);
+    result = Some(if opts.icnf {
+      incremental(&arg, &opts)
+    } else if opts.threads > 1 {
+      multi_threaded(&arg, &opts)
+    } else {
+      single_threaded(&arg, &opts)
+    });
   }
   // println!("Total elapsed for all: {:?}", start.elapsed());
+  if opts.competition {
+    // exit codes from the SAT competition rules
+    process::exit(match result {
+      Some(SolveResult::Sat(_)) => 10,
+      Some(SolveResult::Unsat) => 20,
+      _ => 0,
+    });
+  }
 }
 
-#[allow(dead_code)]
-fn single_threaded(s: &'_ str) {
-  let mut solver = Solver::from_dimacs(s).expect("Could not open dimacs file");
-  let result = solver.solve();
-  solver.stats.csv(s, 1, result.is_some());
-  solver.stats.rate(Duration::from_secs(1));
+/// Formats literals as DIMACS integers, numbering variables from 1
+fn dimacs_lits(lits: &[Literal]) -> String {
+  lits
+    .iter()
+    .map(|lit| {
+      let var = lit.var() as i64 + 1;
+      if lit.negated() {
+        (-var).to_string()
+      } else {
+        var.to_string()
+      }
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Prints the assumptions which made a solver return UNSAT, if it was given any
+fn print_failed(result: &SolveResult, failed: &[Literal], opts: &Options) {
+  if result.is_unsat() && !opts.assumptions.is_empty() {
+    println!("c failed assumptions: {}", dimacs_lits(failed));
+  }
+}
+
+/// Prints a result in the SAT competition output format.
+fn print_competition(result: &SolveResult, elapsed: Duration) -> io::Result<()> {
+  let stdout = io::stdout();
+  let mut out = stdout.lock();
+  writeln!(out, "c solved in {:?}", elapsed)?;
   match result {
-    None => (), // println!("{} UNSAT", s),
-    Some(sol) => {
-      assert!(solver.db.initial_clauses.iter().all(|c| c.is_sat(&sol)));
-      // println!("{} SAT", s);
+    SolveResult::Unknown => writeln!(out, "s UNKNOWN"),
+    SolveResult::Unsat => writeln!(out, "s UNSATISFIABLE"),
+    SolveResult::Sat(model) => {
+      writeln!(out, "s SATISFIABLE")?;
+      write_model(&mut out, model)
     },
-  };
+  }
 }
 
-#[allow(dead_code)]
-fn multi_threaded(s: &'_ str, num_cores: usize) {
+fn open(s: &'_ str, opts: &Options) -> Solver {
+  let mut solver = SolverBuilder::default()
+    .deterministic(opts.deterministic)
+    .share_clauses(opts.share_clauses)
+    .build_from_dimacs(s)
+    .expect("Could not open dimacs file");
+  for &lit in opts.assumptions.iter() {
+    assert!(
+      lit.unsigned_abs() as usize <= solver.num_vars(),
+      "assumed literal {} is not in the formula",
+      lit
+    );
+    solver.push_assumption(Literal::from(lit));
+  }
+  if opts.progress {
+    solver.set_progress(
+      10_000,
+      Box::new(|stats| {
+        let conflicts = stats.clauses_learned as f64;
+        let secs = stats.start_time.elapsed().as_secs_f64();
+        eprintln!("c conflicts {} ({:.0}/s)", conflicts, conflicts / secs);
+      }),
+    );
+  }
+  solver
+}
+
+/// Terminates every solver sharing db once the timeout in opts has elapsed.
+/// The timer does not hold the database itself, so clauses can still be added to it.
+fn stop_after(db: &ClauseDatabase, opts: &Options) {
+  if let Some(timeout) = opts.timeout {
+    let terminator = db.terminator();
+    thread::spawn(move || {
+      thread::sleep(timeout);
+      terminator.terminate();
+    });
+  }
+}
+
+fn single_threaded(s: &'_ str, opts: &Options) -> SolveResult {
+  let start = Instant::now();
+  let mut solver = open(s, opts);
+  stop_after(&solver.db, opts);
+  let result = solver.solve();
+  if let SolveResult::Sat(sol) = &result {
+    assert!(solver.verify(sol));
+  }
+  print_failed(&result, solver.failed_assumptions(), opts);
+  if opts.competition {
+    print_competition(&result, start.elapsed()).expect("Failed to write result");
+    return result;
+  }
+  solver.stats.csv(s, 1, &result);
+  solver.stats.rate(Duration::from_secs(1));
+  println!("{}", solver.db.stats());
+  result
+}
+
+fn multi_threaded(s: &'_ str, opts: &Options) -> SolveResult {
   use std::sync::mpsc::channel;
-  let core_ids = core_affinity::get_core_ids().expect("Could not get core ids");
-  let mut solvers = Solver::from_dimacs(s)
-    .expect("Could not open dimacs file")
-    .replicate(core_ids.len())
+  let start = Instant::now();
+  let solvers = open(s, opts)
+    .replicate(opts.threads)
     .expect("Failed to replicate solver");
-  let initials = solvers[0].db.initial_clauses.clone();
+  let core_ids = if opts.affinity {
+    core_affinity::get_core_ids().expect("Could not get core ids")
+  } else {
+    vec![]
+  };
+  let db = solvers[0].db.clone();
+  stop_after(&db, opts);
   let (sender, receiver) = channel();
-  let name = s.to_owned();
-  let children = core_ids
+  let num_threads = opts.threads;
+  let competition = opts.competition;
+  let assumed = !opts.assumptions.is_empty();
+  let children = solvers
     .into_iter()
-    .take(num_cores)
-    .map(move |id| {
-      let mut solver = solvers.pop().expect("There were less solvers than cores?");
+    .enumerate()
+    .map(|(i, mut solver)| {
+      let core_id = core_ids.get(i % core_ids.len().max(1)).copied();
       let sender = sender.clone();
-      let name = name.clone();
+      let name = s.to_owned();
       thread::spawn(move || {
-        core_affinity::set_for_current(id);
+        if let Some(id) = core_id {
+          core_affinity::set_for_current(id);
+        }
         // Safe to ignore error here because only care about first that finishes
         let result = solver.solve();
-        solver.stats.csv(name, num_cores, result.is_some());
-        let _ = sender.send(result);
+        if !competition {
+          solver.stats.csv(name, num_threads, &result);
+        }
+        let unsat = result.is_unsat();
+        let found = solver.found_by_self().then_some(i);
+        let _ = sender.send((result, solver.failed_assumptions().to_vec(), found));
+        // UNSAT under assumptions is not recorded in the database, so stop the others once
+        // this result was sent ahead of theirs
+        if assumed && unsat {
+          solver.db.terminate();
+        }
       })
     })
     .collect::<Vec<_>>();
@@ -60,13 +256,92 @@ fn multi_threaded(s: &'_ str, num_cores: usize) {
     child.join().unwrap()
   }
 
-  match receiver.recv().expect("FAILED?") {
-    None => {
-      // println!("{} UNSAT", s);
-    },
-    Some(sol) => {
-      assert!(initials.iter().all(|clause| clause.is_sat(&sol)));
-      // println!("{} SAT", s);
-    },
-  };
+  // Every solver returns unknown if the timeout was hit before any of them finished
+  let finished = receiver.try_iter().collect::<Vec<_>>();
+  let (result, failed, _) = finished.first().cloned().expect("No solver finished");
+  if let SolveResult::Sat(sol) = &result {
+    assert!(db.verify(sol));
+  }
+  print_failed(&result, &failed, opts);
+  if let Some(i) = finished.iter().find_map(|&(_, _, found)| found) {
+    println!("c solved by solver {}", i);
+  }
+  if opts.competition {
+    print_competition(&result, start.elapsed()).expect("Failed to write result");
+    return result;
+  }
+  println!("{}", db.stats());
+  result
+}
+
+/// Solves each query of an iCNF file in order with a single solver, adding the clauses between
+/// queries as they are reached. Returns the result of the last query.
+fn incremental(s: &'_ str, opts: &Options) -> SolveResult {
+  let (lines, max_var) = from_icnf(s).expect("Could not open icnf file");
+  let mut lines = lines.into_iter().peekable();
+  let mut initial = vec![];
+  while let Some(IcnfLine::Clause(lits)) = lines.next_if(|line| matches!(line, IcnfLine::Clause(_)))
+  {
+    initial.push(lits);
+  }
+  let mut solver = SolverBuilder::default()
+    .deterministic(opts.deterministic)
+    .build_from_clauses(initial, max_var);
+  stop_after(&solver.db, opts);
+  let mut result = SolveResult::Unknown;
+  for line in lines {
+    let assumptions = match line {
+      IcnfLine::Clause(lits) => {
+        assert!(solver.add_clause(lits.into_iter().map(Literal::from).collect()));
+        continue;
+      },
+      IcnfLine::Assume(assumptions) => assumptions,
+    };
+    for &lit in assumptions.iter() {
+      solver.push_assumption(Literal::from(lit));
+    }
+    result = solver.solve();
+    match &result {
+      SolveResult::Unknown => println!("s UNKNOWN"),
+      SolveResult::Unsat => {
+        println!("s UNSATISFIABLE");
+        if !assumptions.is_empty() {
+          println!(
+            "c failed assumptions: {}",
+            dimacs_lits(solver.failed_assumptions())
+          );
+        }
+      },
+      SolveResult::Sat(sol) => {
+        assert!(solver.verify(sol));
+        println!("s SATISFIABLE");
+      },
+    }
+    for _ in assumptions {
+      solver.pop_assumption();
+    }
+  }
+  result
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  #[test]
+  fn incremental_with_timeout() {
+    let opts = Options {
+      progress: false,
+      competition: false,
+      threads: 1,
+      affinity: false,
+      timeout: Some(Duration::from_secs(5)),
+      deterministic: None,
+      share_clauses: true,
+      assumptions: vec![],
+      icnf: true,
+    };
+    // the timer must not keep clauses from being added between queries
+    let result = incremental("src/bin/data/small/incremental.icnf", &opts);
+    assert_eq!(result, SolveResult::Unsat);
+  }
 }
@@ -0,0 +1,149 @@
+use crate::{
+  database::ClauseDatabase,
+  solver::{Phase, ReducePolicy, Solver, SolverConfig, WatchSelection},
+};
+use std::{io, path::Path, sync::Arc};
+
+/// Builds a solver with chosen search parameters, starting from the defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SolverBuilder {
+  config: SolverConfig,
+}
+
+impl SolverBuilder {
+  pub fn new() -> Self { Self::default() }
+  /// Sets the number of conflicts in the first restart interval
+  pub fn restart_base(mut self, base: u64) -> Self {
+    self.config.restart_base = base;
+    self
+  }
+  /// Sets the base of the luby sequence scaling each restart interval
+  pub fn restart_inc(mut self, inc: u64) -> Self {
+    self.config.restart_inc = inc;
+    self
+  }
+  /// Sets the initial learnt clause limit as a fraction of the number of initial clauses
+  pub fn learntsize_factor(mut self, factor: f64) -> Self {
+    self.config.learntsize_factor = factor;
+    self
+  }
+  /// Sets the growth of the learnt clause limit each time learnt clauses are cleaned
+  pub fn learntsize_inc(mut self, inc: f64) -> Self {
+    self.config.learntsize_inc = inc;
+    self
+  }
+  /// Sets the rate variable activities are divided by after each conflict
+  pub fn var_decay(mut self, decay: f32) -> Self {
+    self.config.var_decay = decay;
+    self
+  }
+  /// Sets the initial polarity of every variable
+  pub fn phase(mut self, phase: Phase) -> Self {
+    self.config.phase = phase;
+    self
+  }
+  /// Sets whether variables are decided with the polarity they last had
+  pub fn phase_saving(mut self, phase_saving: bool) -> Self {
+    self.config.phase_saving = phase_saving;
+    self
+  }
+  /// Sets the seed for the solver's random number generator
+  pub fn seed(mut self, seed: u64) -> Self {
+    self.config.seed = seed;
+    self
+  }
+  /// Sets the fraction of learnt clauses kept each time learnt clauses are cleaned
+  pub fn clause_keep(mut self, keep: f64) -> Self {
+    self.config.clause_keep = keep;
+    self
+  }
+  /// Sets the amount a clause's activity is increased by each time it is used in conflict
+  /// analysis
+  pub fn clause_bump(mut self, bump: u64) -> Self {
+    self.config.clause_bump = bump;
+    self
+  }
+  /// Sets the fraction of its activity every learnt clause keeps each time learnt clauses are
+  /// cleaned
+  pub fn clause_decay(mut self, decay: f64) -> Self {
+    self.config.clause_decay = decay;
+    self
+  }
+  /// Sets whether learnt clauses are checked for tautologies, which are then dropped
+  pub fn check_learnts(mut self, check: bool) -> Self {
+    self.config.check_learnts = check;
+    self
+  }
+  /// Sets the most restarts performed until the solver is reset, or none for no limit
+  pub fn max_restarts(mut self, max: Option<u32>) -> Self {
+    self.config.max_restarts = max;
+    self
+  }
+  /// Sets the number of restarts between resets of the saved phases, or none to never reset
+  /// them
+  pub fn rephase_interval(mut self, interval: Option<u32>) -> Self {
+    self.config.rephase_interval = interval;
+    self
+  }
+  /// Sets the phases the saved phases are reset to in turn when rephasing
+  pub fn rephase_policy(mut self, policy: &'static [Phase]) -> Self {
+    self.config.rephase_policy = policy;
+    self
+  }
+  /// Exchanges learnt clauses between solvers only at a rendezvous every interval conflicts,
+  /// so that parallel runs are reproducible, or whenever possible if none.
+  pub fn deterministic(mut self, interval: Option<u64>) -> Self {
+    self.config.deterministic = interval;
+    self
+  }
+  /// Sets whether learnt clauses are shared with other solvers through the database
+  pub fn share_clauses(mut self, share: bool) -> Self {
+    self.config.share_clauses = share;
+    self
+  }
+  /// Sets whether learnt clauses are kept, or only used to backjump
+  pub fn learning(mut self, learning: bool) -> Self {
+    self.config.learning = learning;
+    self
+  }
+  /// Sets which literals each clause watches when watch lists are built
+  pub fn watch_selection(mut self, selection: WatchSelection) -> Self {
+    self.config.watch_selection = selection;
+    self
+  }
+  /// Sets the most literals in a learnt clause which is kept, or none for no limit
+  pub fn max_learnt_len(mut self, max: Option<usize>) -> Self {
+    self.config.max_learnt_len = max;
+    self
+  }
+  /// Sets whether every learnt clause is kept instead of periodically deleting inactive ones
+  pub fn keep_all_learnt(mut self, keep: bool) -> Self {
+    self.config.keep_all_learnt = keep;
+    self
+  }
+  /// Sets when the least active learnt clauses are deleted.
+  /// Panics if the policy is a fixed interval of 0 conflicts.
+  pub fn reduce_policy(mut self, policy: ReducePolicy) -> Self {
+    assert_ne!(
+      policy,
+      ReducePolicy::FixedInterval(0),
+      "learnt clauses must be reduced every few conflicts"
+    );
+    self.config.reduce_policy = policy;
+    self
+  }
+  /// Returns the config built so far
+  pub fn config(&self) -> SolverConfig { self.config }
+  /// Builds a solver for the formula in the given DIMACS file
+  pub fn build_from_dimacs<S: AsRef<Path>>(&self, s: S) -> io::Result<Solver> {
+    Solver::from_dimacs_with(s, &self.config)
+  }
+  /// Builds a solver sharing an existing database, see `Solver::with_database`
+  pub fn build_with_database(&self, db: Arc<ClauseDatabase>) -> Solver {
+    Solver::from_database_with(db, &self.config)
+  }
+  /// Builds a solver for the clauses given as DIMACS literals, see `Solver::from_clauses`
+  pub fn build_from_clauses(&self, clauses: Vec<Vec<i32>>, max_var: usize) -> Solver {
+    Solver::from_clauses_with(clauses, max_var, &self.config)
+  }
+}
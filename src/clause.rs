@@ -17,6 +17,12 @@ pub struct Clause {
   pub(crate) initial: bool,
   /// Clause activity, used for compaction
   pub(crate) activity: Arc<AtomicU64>,
+  /// Id of this clause in proofs, where initial clauses are numbered from 1 in parse order.
+  /// 0 if this clause was never numbered.
+  pub(crate) id: u64,
+  /// Number of times this clause became unit and propagated its remaining literal
+  #[cfg(feature = "clause-stats")]
+  pub(crate) propagations: AtomicU64,
 }
 
 impl PartialEq for Clause {
@@ -24,12 +30,10 @@ impl PartialEq for Clause {
 }
 impl Eq for Clause {}
 impl PartialOrd for Clause {
-  fn partial_cmp(&self, o: &Self) -> Option<std::cmp::Ordering> {
-    self.literals.partial_cmp(&o.literals)
-  }
+  fn partial_cmp(&self, o: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(o)) }
 }
 impl Ord for Clause {
-  fn cmp(&self, o: &Self) -> std::cmp::Ordering { self.partial_cmp(&o).unwrap() }
+  fn cmp(&self, o: &Self) -> std::cmp::Ordering { self.literals.cmp(&o.literals) }
 }
 
 impl Hash for Clause {
@@ -37,6 +41,10 @@ impl Hash for Clause {
 }
 
 impl Clause {
+  /// Returns the id of this clause. Initial clauses are numbered from 1 in parse order, and
+  /// learnt clauses after them by the solver which derived them, so ids are the same across
+  /// runs. Clauses made by preprocessing or added later are 0.
+  pub fn id(&self) -> u64 { self.id }
   /// returns true if this clause has no literals.
   pub fn is_empty(&self) -> bool { self.literals.is_empty() }
   pub fn with_capacity(cap: usize) -> Self {
@@ -44,18 +52,30 @@ impl Clause {
       literals: Vec::with_capacity(cap),
       initial: false,
       activity: Arc::new(AtomicU64::new(0)),
+      id: 0,
+      #[cfg(feature = "clause-stats")]
+      propagations: AtomicU64::new(0),
+    }
+  }
+  /// Creates a clause from literals which are already sorted and contain no duplicates,
+  /// skipping the work done by `Clause::from`. This is only checked in debug builds.
+  pub fn from_sorted_unchecked(lits: Vec<Literal>) -> Self {
+    debug_assert!(
+      lits.windows(2).all(|pair| pair[0] < pair[1]),
+      "literals are not sorted and unique"
+    );
+    Self {
+      literals: lits,
+      ..Self::with_capacity(0)
     }
   }
   /// Returns true if this clause contains both a literal and its negation.
+  /// Since literals are sorted, a literal and its negation are always adjacent.
   pub fn is_tautology(&self) -> bool {
-    let mut seen: Vec<&Literal> = Vec::with_capacity(self.literals.len());
-    self.literals.iter().any(|lit| {
-      if seen.iter().any(|prev| prev.is_negation(*lit)) {
-        return true;
-      };
-      seen.push(lit);
-      false
-    })
+    self
+      .literals
+      .windows(2)
+      .any(|pair| pair[0].is_negation(pair[1]))
   }
   /// returns true if any literal is true based on the assignment vector
   pub fn is_sat(&self, final_assns: &[bool]) -> bool {
@@ -64,10 +84,60 @@ impl Clause {
       .iter()
       .any(|lit| final_assns[lit.var()] ^ lit.negated())
   }
-  /// Increases the ordering of this clause
-  pub fn boost(&self) { self.activity.fetch_add(1, Ordering::SeqCst); }
+  /// Evaluates this clause under a partial assignment, returning true if any literal is true,
+  /// false if every literal is false, and none if it depends on unassigned variables.
+  pub fn value_under(&self, assns: &[Option<bool>]) -> Option<bool> {
+    let mut value = Some(false);
+    for lit in self.literals.iter() {
+      match lit.assn(assns) {
+        Some(true) => return Some(true),
+        Some(false) => {},
+        None => value = None,
+      }
+    }
+    value
+  }
+  /// Returns true if every literal in this clause is also in o, meaning that o is implied by
+  /// this clause. Relies on both clauses having sorted literals.
+  pub fn subsumes(&self, o: &Self) -> bool {
+    if self.literals.len() > o.literals.len() {
+      return false;
+    }
+    let mut others = o.literals.iter();
+    self
+      .literals
+      .iter()
+      .all(|lit| others.by_ref().any(|o_lit| o_lit == lit))
+  }
+  /// If this clause is (x | rest) and o is (!x | rest_o) where rest is a subset of rest_o,
+  /// returns !x, which can be removed from o by self-subsuming resolution.
+  pub fn subsumption_resolvable(&self, o: &Self) -> Option<Literal> {
+    if self.literals.len() > o.literals.len() {
+      return None;
+    }
+    let mut resolved = None;
+    for lit in self.literals.iter() {
+      if o.literals.binary_search(lit).is_ok() {
+        continue;
+      }
+      if resolved.is_some() || o.literals.binary_search(&!lit).is_err() {
+        return None;
+      }
+      resolved = Some(!lit);
+    }
+    // a tautology containing both x and !x cannot strengthen o
+    resolved.filter(|lit| self.literals.binary_search(lit).is_err())
+  }
+  /// Increases the activity of this clause by amt
+  pub fn boost(&self, amt: u64) { self.activity.fetch_add(amt, Ordering::SeqCst); }
   /// SeqCst Atomic load of the activity for this clause
   pub fn curr_activity(&self) -> u64 { self.activity.load(Ordering::SeqCst) }
+  /// Records that this clause propagated its remaining literal
+  #[cfg(feature = "clause-stats")]
+  pub(crate) fn record_propagation(&self) { self.propagations.fetch_add(1, Ordering::Relaxed); }
+  /// Returns the number of times this clause propagated its remaining literal
+  #[cfg(feature = "clause-stats")]
+  pub fn propagations(&self) -> u64 { self.propagations.load(Ordering::Relaxed) }
 }
 
 impl From<Vec<Literal>> for Clause {
@@ -79,7 +149,24 @@ impl From<Vec<Literal>> for Clause {
       literals: lits,
       initial: false,
       activity: Arc::new(AtomicU64::new(0)),
+      id: 0,
+      #[cfg(feature = "clause-stats")]
+      propagations: AtomicU64::new(0),
+    }
+  }
+}
+
+/// Shows disjuncted literals with negations
+impl fmt::Display for Clause {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "(")?;
+    if !self.literals.is_empty() {
+      write!(f, "{}", self.literals[0])?;
+      for lit in self.literals.iter().skip(1) {
+        write!(f, " | {}", lit)?;
+      }
     }
+    write!(f, ")")
   }
 }
 
@@ -95,18 +182,46 @@ mod test {
     assert!(tautology().is_tautology());
     assert!(!example_clause().is_tautology());
   }
-}
-
-/// Shows disjuncted literals with negations
-impl fmt::Display for Clause {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(f, "(")?;
-    if !self.literals.is_empty() {
-      write!(f, "{}", self.literals[0])?;
-      for lit in self.literals.iter().skip(1) {
-        write!(f, " | {}", lit)?;
-      }
-    }
-    write!(f, ")")
+  #[test]
+  fn check_subsumes() {
+    let shorter = Clause::from(vec![Literal::from(2), Literal::from(-3)]);
+    assert!(shorter.subsumes(&example_clause()));
+    assert!(!example_clause().subsumes(&shorter));
+    assert!(example_clause().subsumes(&example_clause()));
+    assert!(!tautology().subsumes(&example_clause()));
+  }
+  #[test]
+  fn check_subsumption_resolvable() {
+    let resolvable = Clause::from(vec![Literal::from(1), Literal::from(2)]);
+    assert_eq!(
+      resolvable.subsumption_resolvable(&example_clause()),
+      Some(Literal::from(-1))
+    );
+    assert_eq!(example_clause().subsumption_resolvable(&resolvable), None);
+    assert_eq!(tautology().subsumption_resolvable(&example_clause()), None);
+  }
+  #[test]
+  fn check_value_under() {
+    // (!1 | 2 | !3)
+    let clause = example_clause();
+    assert_eq!(clause.value_under(&[None; 3]), None);
+    assert_eq!(clause.value_under(&[Some(true), None, Some(true)]), None);
+    assert_eq!(
+      clause.value_under(&[Some(true), Some(false), Some(true)]),
+      Some(false)
+    );
+    assert_eq!(clause.value_under(&[None, Some(true), None]), Some(true));
+    assert_eq!(Clause::from(vec![]).value_under(&[]), Some(false));
+  }
+  #[test]
+  fn sorted_unchecked() {
+    let sorted = example_clause().literals;
+    assert_eq!(Clause::from_sorted_unchecked(sorted), example_clause());
+  }
+  #[cfg(debug_assertions)]
+  #[test]
+  #[should_panic(expected = "not sorted")]
+  fn unsorted_unchecked() {
+    Clause::from_sorted_unchecked(vec![Literal::from(2), Literal::from(1)]);
   }
 }
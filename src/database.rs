@@ -1,17 +1,86 @@
-use crate::{clause::Clause, literal::Literal};
+use crate::{clause::Clause, literal::Literal, model::ModelExtension};
 use std::{
+  fmt,
   hash::{Hash, Hasher},
   ops::Deref,
-  sync::{Arc, RwLock, Weak},
+  sync::{
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Arc, Condvar, Mutex, RwLock, Weak,
+  },
 };
 
 // maybe want some append only log?
 
+/// The most clause comparisons `ClauseDatabase::subsume` will perform.
+pub const SUBSUMPTION_LIMIT: usize = 10_000_000;
+
+/// Learnt clauses written by a single solver.
+/// Each clause is stamped with its write index, the number of clauses written by the solver
+/// before it. Readers track the write index they have read up to, which stays valid no matter
+/// which clauses are later removed by compaction.
+#[derive(Debug, Default)]
+struct LearntLog {
+  /// number of clauses ever written, which is also the next write index
+  written: usize,
+  /// clauses not yet removed by compaction along with their write index, in write order
+  clauses: Vec<(usize, Weak<Clause>)>,
+  /// number of clauses removed by compaction
+  deleted: usize,
+}
+
+/// Where solvers exchanging clauses deterministically wait for each other.
+#[derive(Debug, Default)]
+struct Rendezvous {
+  state: Mutex<RendezvousState>,
+  changed: Condvar,
+}
+
+impl Rendezvous {
+  /// Lets every waiting solver go, and keeps solvers from waiting until the state is reset
+  fn release(&self) {
+    self.state.lock().unwrap().released = true;
+    self.changed.notify_all();
+  }
+}
+
+/// Stops every solver sharing a database, like `ClauseDatabase::terminate`, without keeping the
+/// database shared, so that clauses can still be added to it while this is held.
+#[derive(Debug, Clone)]
+pub struct Terminator {
+  stopped: Arc<AtomicBool>,
+  rendezvous: Arc<Rendezvous>,
+}
+
+impl Terminator {
+  /// Stops every solver sharing the database, which return unknown unless a solution was
+  /// added. Unlike `ClauseDatabase::terminate`, forgetting the solution does not undo this,
+  /// so every later solve stops as well.
+  pub fn terminate(&self) {
+    self.stopped.store(true, Ordering::Release);
+    self.rendezvous.release();
+  }
+}
+
+#[derive(Debug, Default)]
+struct RendezvousState {
+  /// number of solvers which have not left
+  parties: usize,
+  /// number of solvers waiting for the rest to arrive
+  arrived: usize,
+  /// number of times every solver arrived
+  generation: u64,
+  /// set once solvers should stop, after which none wait for the others to arrive
+  released: bool,
+}
+
 #[derive(Debug)]
 pub struct ClauseDatabase {
   // the max number of variables in this set of clauses
   pub(crate) max_var: usize,
   curr_id: RwLock<usize>,
+  /// One more than the highest clause id any solver has derived, where solvers replicated
+  /// from then on start numbering their clauses
+  next_clause_id: AtomicU64,
 
   // initial set of read only clauses.
   // They should only be simplified to be equivalent to
@@ -23,47 +92,226 @@ pub struct ClauseDatabase {
 
   // Learnt clauses from each solver and the clock # of the latest clause.
   // The clock # must be explicitly tracked since the database might be compacted.
-  // .0 is num written
-  // .1 is the actual data
-  // .2 is the number deleted
-  learnt_clauses: Vec<RwLock<(usize, Vec<Weak<Clause>>, usize)>>,
+  learnt_clauses: Vec<RwLock<LearntLog>>,
+
+  /// Variables eliminated by resolution, which are needed to extend a model to these variables.
+  eliminated: ModelExtension,
+
+  /// The most learnt clauses which can be stored across all solvers
+  max_learnt: AtomicUsize,
+  /// The number of learnt clauses stored across all solvers, including freed clauses which
+  /// have not been compacted yet.
+  num_learnt: AtomicUsize,
 
   /// A short circuited solution
-  /// Is a nested option to indicate no solution found or
-  /// there is no solution.
-  pub(crate) solution: RwLock<Option<Option<Vec<bool>>>>,
+  pub(crate) solution: RwLock<DbSolution>,
+  /// The exchange interval and id of the solver which found the solution, if it was found
+  /// in deterministic mode
+  solved_at: Mutex<Option<(u64, usize)>>,
+  /// Set once a solution is added, so that solvers sharing this database stop searching
+  terminated: AtomicBool,
+  /// Set by a `Terminator`, which stops solvers for good
+  stopped: Arc<AtomicBool>,
+  rendezvous: Arc<Rendezvous>,
 }
 
 impl ClauseDatabase {
-  pub fn new(max_var: usize, mut initial_clauses: Vec<Clause>) -> Self {
-    let learnt_clauses = vec![RwLock::new((0, vec![], 0))];
+  /// Creates a database of the given clauses over variables below max_var.
+  /// Panics if a clause contains a larger variable, see `try_new`.
+  pub fn new(max_var: usize, initial_clauses: Vec<Clause>) -> Self {
+    Self::try_new(max_var, initial_clauses).unwrap_or_else(|err| panic!("{}", err))
+  }
+  /// Creates a database of the given clauses over variables below max_var, or returns an
+  /// error if a clause contains a larger variable.
+  pub fn try_new(max_var: usize, initial_clauses: Vec<Clause>) -> Result<Self, VarOutOfRange> {
+    for (clause, clause_lits) in initial_clauses.iter().enumerate() {
+      if let Some(lit) = clause_lits.literals.iter().find(|lit| lit.var() >= max_var) {
+        return Err(VarOutOfRange {
+          clause,
+          var: lit.var(),
+          max_var,
+        });
+      }
+    }
+    Ok(Self::new_unchecked(max_var, initial_clauses))
+  }
+  fn new_unchecked(max_var: usize, mut initial_clauses: Vec<Clause>) -> Self {
+    let learnt_clauses = vec![RwLock::new(LearntLog::default())];
+    let num_parsed = initial_clauses.len();
+    for (i, clause) in initial_clauses.iter_mut().enumerate() {
+      clause.id = i as u64 + 1;
+    }
     // Can't trust these darned CNF files
+    initial_clauses.retain(|clause| !clause.is_tautology());
     initial_clauses.sort_unstable();
     initial_clauses.dedup();
     Self {
       curr_id: RwLock::new(0),
+      next_clause_id: AtomicU64::new(num_parsed as u64 + 1),
       max_var,
       initial_clauses: initial_clauses.into_iter().map(Arc::new).collect(),
       learnt_clauses,
-      solution: RwLock::new(None),
+      eliminated: ModelExtension::default(),
+      max_learnt: AtomicUsize::new(usize::MAX),
+      num_learnt: AtomicUsize::new(0),
+      solution: RwLock::new(DbSolution::Undecided),
+      solved_at: Mutex::new(None),
+      terminated: AtomicBool::new(false),
+      stopped: Arc::new(AtomicBool::new(false)),
+      rendezvous: Arc::new(Rendezvous {
+        state: Mutex::new(RendezvousState {
+          parties: 1,
+          ..Default::default()
+        }),
+        changed: Condvar::new(),
+      }),
+    }
+  }
+  /// Adds a solution to this database, which must be SAT or UNSAT
+  pub fn add_solution(&self, sol: DbSolution) {
+    assert_ne!(sol, DbSolution::Undecided, "added an undecided solution");
+    let mut solution = self.solution.write().unwrap();
+    *solution = sol;
+    self.solved_at.lock().unwrap().replace((0, 0));
+    drop(solution);
+    self.terminate();
+  }
+  /// Adds a solution found by solver id in the given exchange interval of deterministic mode.
+  /// Solvers may finish in the same interval, so only the solution from the earliest interval
+  /// and lowest id is kept, which does not depend on which thread was faster.
+  pub(crate) fn add_solution_at(&self, interval: u64, id: usize, sol: DbSolution) {
+    assert_ne!(sol, DbSolution::Undecided, "added an undecided solution");
+    let mut solution = self.solution.write().unwrap();
+    let mut solved_at = self.solved_at.lock().unwrap();
+    if solved_at.is_none_or(|earlier| (interval, id) < earlier) {
+      *solution = sol;
+      solved_at.replace((interval, id));
+    }
+    drop((solution, solved_at));
+    self.terminate();
+  }
+  /// Returns the id of the solver whose solution was kept in deterministic mode, or none if
+  /// there is no solution yet. Outside of deterministic mode solutions are always attributed
+  /// to solver 0, so this is meaningless.
+  pub(crate) fn solved_by(&self) -> Option<usize> {
+    self.solved_at.lock().unwrap().map(|(_, id)| id)
+  }
+  /// Stops every solver sharing this database, which return unknown unless a solution was
+  /// added.
+  pub fn terminate(&self) {
+    self.terminated.store(true, Ordering::Release);
+    self.rendezvous.release();
+  }
+  /// Returns a handle which stops every solver sharing this database from another thread,
+  /// such as once a timeout elapses, see `Terminator`.
+  pub fn terminator(&self) -> Terminator {
+    Terminator {
+      stopped: self.stopped.clone(),
+      rendezvous: self.rendezvous.clone(),
+    }
+  }
+  /// Waits until every solver sharing this database arrives.
+  /// Returns false without waiting for the rest if solvers were told to stop.
+  pub(crate) fn rendezvous(&self) -> bool {
+    let mut state = self.rendezvous.state.lock().unwrap();
+    if state.released {
+      return false;
+    }
+    state.arrived += 1;
+    if state.arrived == state.parties {
+      state.arrived = 0;
+      state.generation += 1;
+      self.rendezvous.changed.notify_all();
+      return true;
     }
+    let generation = state.generation;
+    let state = self
+      .rendezvous
+      .changed
+      .wait_while(state, |state| {
+        state.generation == generation && !state.released
+      })
+      .unwrap();
+    // a solver released after everyone arrived still continues, as the others do
+    state.generation != generation
   }
-  /// Adds a solution to this database
-  pub fn add_solution(&self, sol: Option<Vec<bool>>) {
-    self.solution.write().unwrap().replace(sol);
+  /// Stops waiting for this solver at a rendezvous, and waits for every other solver to leave
+  /// as well, so that all of them see the same solution.
+  pub(crate) fn leave(&self) {
+    let mut state = self.rendezvous.state.lock().unwrap();
+    state.released = true;
+    state.parties = state.parties.saturating_sub(1);
+    self.rendezvous.changed.notify_all();
+    let _state = self
+      .rendezvous
+      .changed
+      .wait_while(state, |state| state.parties > 0)
+      .unwrap();
   }
-  pub fn get_solution(&self) -> Option<Option<Vec<bool>>> {
-    self.solution.read().unwrap().as_ref().cloned()
+  /// Returns true if a solution was added or this was terminated, meaning that solvers should
+  /// stop searching
+  #[inline]
+  pub fn terminated(&self) -> bool {
+    self.terminated.load(Ordering::Acquire) || self.stopped.load(Ordering::Acquire)
   }
+  /// Returns the solution added to this database so far
+  pub fn get_solution(&self) -> DbSolution { self.solution.read().unwrap().clone() }
   /// adds a batch of learnt clauses to the database and returns the new timestamp of the
   /// process
+  /// If adding the batch would exceed the learnt clause budget, the batch is discarded instead.
   pub fn add_learnts(&self, id: usize, c: &mut Vec<ClauseRef>) -> usize {
+    // reserve room for the whole batch at once, so that solvers adding batches at the same
+    // time cannot exceed the budget together
+    let max_learnt = self.max_learnt.load(Ordering::SeqCst);
+    let reserved = self
+      .num_learnt
+      .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |num_learnt| {
+        num_learnt
+          .checked_add(c.len())
+          .filter(|&total| total <= max_learnt)
+      });
+    if reserved.is_err() {
+      c.clear();
+      return self.learnt_clauses[id].read().unwrap().written;
+    }
     let mut learnt_clauses = self.learnt_clauses[id].write().unwrap();
-    learnt_clauses.0 += c.len();
-    learnt_clauses
-      .1
-      .extend(c.drain(..).map(|cref| Arc::downgrade(&cref.inner)));
-    learnt_clauses.0
+    let start = learnt_clauses.written;
+    learnt_clauses.written += c.len();
+    learnt_clauses.clauses.extend(
+      c.drain(..)
+        .enumerate()
+        .map(|(i, cref)| (start + i, Arc::downgrade(&cref.inner))),
+    );
+    learnt_clauses.written
+  }
+  /// Sets the most learnt clauses which can be stored across all solvers sharing this
+  /// database. Once the limit is reached, solvers clean their learnt clauses more aggressively
+  /// and new clauses are not shared until enough have been deleted.
+  pub fn set_max_learnt(&self, n: usize) { self.max_learnt.store(n, Ordering::SeqCst); }
+  /// Returns whether the number of stored learnt clauses has reached the limit.
+  pub fn learnt_over_budget(&self) -> bool {
+    self.num_learnt.load(Ordering::SeqCst) >= self.max_learnt.load(Ordering::SeqCst)
+  }
+  /// Returns the current size of this database.
+  pub fn stats(&self) -> DbStats {
+    let (live_learnt, deleted_learnt) = self
+      .learnt_clauses
+      .iter()
+      .map(|learnt| {
+        let learnt = learnt.read().unwrap();
+        let live = learnt
+          .clauses
+          .iter()
+          .filter(|(_, weak)| weak.strong_count() > 0)
+          .count();
+        (live, learnt.deleted)
+      })
+      .fold((0, 0), |(live, deleted), (l, d)| (live + l, deleted + d));
+    DbStats {
+      initial: self.initial_clauses.len(),
+      live_learnt,
+      deleted_learnt,
+    }
   }
   /// returns the number of solvers expected for this database
   pub fn num_solvers(&self) -> usize { self.learnt_clauses.len() }
@@ -72,33 +320,288 @@ impl ClauseDatabase {
     *id += 1;
     *id - 1
   }
+  /// Records that a solver derived a clause with the given id.
+  pub(crate) fn issued_clause_id(&self, id: u64) {
+    self.next_clause_id.fetch_max(id + 1, Ordering::Relaxed);
+  }
+  /// Returns the lowest clause id above every id derived so far.
+  pub(crate) fn next_clause_id(&self) -> u64 { self.next_clause_id.load(Ordering::Relaxed) }
 
+  /// Iterates over every initial clause followed by every learnt clause which is still alive.
   pub fn iter(&self) -> impl Iterator<Item = ClauseRef> + '_ {
-    let out = (0..self.initial_clauses.len()).map(move |i| ClauseRef {
-      inner: self.initial_clauses[i].clone(),
-    });
-    let mut new = vec![];
-    self.since(&mut new, &mut vec![0; self.num_solvers()]);
-    out.chain(new.into_iter())
+    self.initial_iter().chain(self.learnt_iter())
+  }
+  /// Returns the n clauses which propagated the most, most first, among the initial clauses
+  /// and the learnt clauses which are still alive.
+  #[cfg(feature = "clause-stats")]
+  pub fn hottest_clauses(&self, n: usize) -> Vec<ClauseRef> {
+    let mut clauses = self.iter().collect::<Vec<_>>();
+    clauses.sort_by_key(|cref| std::cmp::Reverse(cref.propagations()));
+    clauses.truncate(n);
+    clauses
+  }
+  /// Iterates over the initial clauses only.
+  pub fn initial_iter(&self) -> impl Iterator<Item = ClauseRef> + '_ {
+    self.initial_clauses.iter().map(|inner| ClauseRef {
+      inner: inner.clone(),
+    })
   }
   pub fn initial(&self) -> &Vec<Arc<Clause>> { &self.initial_clauses }
-  /// Writes the new clauses into "into", and updates the timestamps.
-  /// Returns the number of clauses written.
-  pub fn since<T: Extend<ClauseRef>>(&self, into: &mut T, times: &mut Vec<usize>) {
+  /// Iterates over every learnt clause written to this database which is still alive.
+  /// Each solver's learnt clauses are read under its lock when the iterator reaches them.
+  pub fn learnt_iter(&self) -> impl Iterator<Item = ClauseRef> + '_ {
+    self.learnt_clauses.iter().flat_map(|learnt| {
+      learnt
+        .read()
+        .unwrap()
+        .clauses
+        .iter()
+        .filter_map(|(_, weak)| weak.upgrade())
+        .map(|inner| ClauseRef { inner })
+        .collect::<Vec<_>>()
+    })
+  }
+  /// Removes initial clauses which are subsumed by another initial clause, returning how many
+  /// were removed.
+  /// Each clause is only compared against clauses indexed by one of its literals, where each
+  /// clause is indexed by its least frequently occurring literal, and the total number of
+  /// comparisons is bounded by `SUBSUMPTION_LIMIT`.
+  pub fn subsume(&mut self) -> usize {
+    let clauses = &self.initial_clauses;
+    let mut occurrences = vec![0usize; self.max_var << 1];
+    clauses
+      .iter()
+      .flat_map(|clause| clause.literals.iter())
+      .for_each(|lit| occurrences[lit.raw() as usize] += 1);
+    let mut index = vec![vec![]; self.max_var << 1];
+    for (i, clause) in clauses.iter().enumerate() {
+      if let Some(lit) = clause
+        .literals
+        .iter()
+        .min_by_key(|lit| occurrences[lit.raw() as usize])
+      {
+        index[lit.raw() as usize].push(i);
+      }
+    }
+    let mut subsumed = vec![false; clauses.len()];
+    let mut comparisons = 0;
+    'clauses: for (i, clause) in clauses.iter().enumerate() {
+      for lit in clause.literals.iter() {
+        for &j in index[lit.raw() as usize].iter() {
+          if i == j || subsumed[j] || clauses[j].literals.len() > clause.literals.len() {
+            continue;
+          }
+          comparisons += 1;
+          if comparisons > SUBSUMPTION_LIMIT {
+            break 'clauses;
+          }
+          if clauses[j].subsumes(clause) {
+            subsumed[i] = true;
+            continue 'clauses;
+          }
+        }
+      }
+    }
+    let original = self.initial_clauses.len();
+    let mut subsumed = subsumed.into_iter();
+    self.initial_clauses.retain(|_| !subsumed.next().unwrap());
+    original - self.initial_clauses.len()
+  }
+  /// Eliminates var by replacing every initial clause containing it with all non-tautological
+  /// resolvents on var, as long as that adds at most grow clauses.
+  /// Returns whether the variable was eliminated.
+  pub fn eliminate(&mut self, var: usize, grow: usize) -> bool {
+    let pos = Literal::new(var as u32, false);
+    let (with_pos, with_neg): (Vec<_>, Vec<_>) = self
+      .initial_clauses
+      .iter()
+      .filter(|clause| clause.literals.iter().any(|lit| lit.var() == var))
+      .partition(|clause| clause.literals.binary_search(&pos).is_ok());
+    if with_pos.is_empty() && with_neg.is_empty() {
+      return false;
+    }
+    let limit = with_pos.len() + with_neg.len() + grow;
+    let mut resolvents = vec![];
+    for p in with_pos.iter() {
+      for n in with_neg.iter() {
+        let mut lits = p
+          .literals
+          .iter()
+          .chain(n.literals.iter())
+          .filter(|lit| lit.var() != var)
+          .copied()
+          .collect::<Vec<_>>();
+        lits.sort_unstable();
+        lits.dedup();
+        if lits.windows(2).any(|pair| pair[0].is_negation(pair[1])) {
+          continue;
+        }
+        // an empty resolvent is a conflict, which is left for the solver to find.
+        if lits.is_empty() || resolvents.len() == limit {
+          return false;
+        }
+        resolvents.push(lits);
+      }
+    }
+    let (removed, rest) = self
+      .initial_clauses
+      .drain(..)
+      .partition(|clause| clause.literals.iter().any(|lit| lit.var() == var));
+    self.initial_clauses = rest;
+    self
+      .initial_clauses
+      .extend(resolvents.into_iter().map(|lits| {
+        let mut clause = Clause::from_sorted_unchecked(lits);
+        clause.initial = true;
+        Arc::new(clause)
+      }));
+    self.eliminated.push(var, removed);
+    true
+  }
+  /// Returns true if model assigns every variable and satisfies every initial clause, including
+  /// those removed by variable elimination.
+  pub fn verify(&self, model: &[bool]) -> bool {
+    model.len() == self.max_var
+      && self
+        .initial_clauses
+        .iter()
+        .chain(self.eliminated.clauses())
+        .all(|clause| clause.is_sat(model))
+  }
+  /// Counts the initial clauses which are satisfied, conflicting, and undetermined under a
+  /// partial assignment, in that order, in time linear in the number of literals.
+  /// Clauses removed by variable elimination are not counted.
+  pub fn clause_status_counts(&self, assns: &[Option<bool>]) -> (usize, usize, usize) {
+    let mut counts = (0, 0, 0);
+    for clause in self.initial_clauses.iter() {
+      match clause.value_under(assns) {
+        Some(true) => counts.0 += 1,
+        Some(false) => counts.1 += 1,
+        None => counts.2 += 1,
+      }
+    }
+    counts
+  }
+  /// Adds a clause to the initial clauses, unless it is a tautology.
+  pub(crate) fn add_initial(&mut self, mut clause: Clause) {
+    if clause.is_tautology() {
+      return;
+    }
+    clause.initial = true;
+    self.initial_clauses.push(Arc::new(clause));
+  }
+  /// returns the number of variables eliminated by resolution
+  pub fn num_eliminated(&self) -> usize { self.eliminated.len() }
+  /// Returns the rules for reconstructing variables removed by bounded variable elimination
+  pub fn model_extension(&self) -> &ModelExtension { &self.eliminated }
+  /// Forgets any solution found so far
+  pub(crate) fn clear_solution(&self) {
+    *self.solution.write().unwrap() = DbSolution::Undecided;
+    self.solved_at.lock().unwrap().take();
+    self.terminated.store(false, Ordering::Release);
+    let mut rendezvous = self.rendezvous.state.lock().unwrap();
+    *rendezvous = RendezvousState {
+      parties: self.num_solvers(),
+      released: self.stopped.load(Ordering::Acquire),
+      ..Default::default()
+    };
+  }
+  /// Strengthens initial clauses with self-subsuming resolution, removing !x from any clause
+  /// (!x | rest_b) where there is another clause (x | rest_a) and rest_a is a subset of rest_b.
+  /// Returns the number of literals removed.
+  pub fn strengthen(&mut self) -> usize {
+    let clauses = &mut self.initial_clauses;
+    let mut occurrences = vec![vec![]; self.max_var << 1];
+    for (i, clause) in clauses.iter().enumerate() {
+      for lit in clause.literals.iter() {
+        occurrences[lit.raw() as usize].push(i);
+      }
+    }
+    let mut removed = 0;
+    let mut comparisons = 0;
+    for i in 0..clauses.len() {
+      let mut k = 0;
+      // never strengthen a unit clause into the empty clause
+      while k < clauses[i].literals.len() && clauses[i].literals.len() > 1 {
+        let lit = clauses[i].literals[k];
+        let clause = &clauses[i];
+        let resolvable = occurrences[(!lit).raw() as usize].iter().any(|&j| {
+          comparisons += 1;
+          i != j
+            && comparisons <= SUBSUMPTION_LIMIT
+            && clauses[j].subsumption_resolvable(clause) == Some(lit)
+        });
+        if !resolvable {
+          k += 1;
+          continue;
+        }
+        let mut literals = clause.literals.clone();
+        literals.remove(k);
+        clauses[i] = shrunk(clause, literals);
+        removed += 1;
+      }
+    }
+    removed
+  }
+  /// Rewrites the initial clauses under the level 0 assignments assns, dropping clauses which
+  /// are satisfied and removing false literals from the rest. A unit clause is added for each
+  /// assigned variable, so that the clauses stay equivalent to the original ones.
+  /// The assignments must be fully propagated without conflict. Returns the number of satisfied
+  /// clauses dropped.
+  pub fn compact_initial(&mut self, assns: &[Option<bool>]) -> usize {
+    let original = self.initial_clauses.len();
+    self.initial_clauses.retain(|clause| {
+      !clause
+        .literals
+        .iter()
+        .any(|lit| lit.assn(assns) == Some(true))
+    });
+    let removed = original - self.initial_clauses.len();
+    for clause in self.initial_clauses.iter_mut() {
+      if clause.literals.iter().all(|lit| lit.assn(assns).is_none()) {
+        continue;
+      }
+      let literals = clause
+        .literals
+        .iter()
+        .filter(|lit| lit.assn(assns).is_none())
+        .copied()
+        .collect::<Vec<_>>();
+      assert!(!literals.is_empty(), "compacted a conflicting clause");
+      *clause = shrunk(clause, literals);
+    }
+    let units = assns
+      .iter()
+      .enumerate()
+      .filter_map(|(var, val)| val.map(|val| Literal::new(var as u32, !val)));
+    for lit in units {
+      let mut unit = Clause::from_sorted_unchecked(vec![lit]);
+      unit.initial = true;
+      self.initial_clauses.push(Arc::new(unit));
+    }
+    removed
+  }
+  /// Writes the clauses each solver wrote at or after the write index in times into "into",
+  /// and updates the times to the latest write index of each solver.
+  /// Solvers whose clauses are currently locked are skipped, and read on a later call.
+  /// Clauses are never copied: each one read shares its storage with the clause written, so
+  /// transferring a clause only costs a reference count.
+  pub fn since<T: Extend<ClauseRef>>(&self, into: &mut T, times: &mut [usize]) {
     assert_eq!(self.learnt_clauses.len(), times.len());
     times.iter_mut().enumerate().for_each(|(i, written)| {
       match &self.learnt_clauses[i].try_read() {
         Err(_) => {},
         Ok(learnt_clauses) => {
+          let start = learnt_clauses
+            .clauses
+            .partition_point(|&(idx, _)| idx < *written);
           into.extend(
-            learnt_clauses
-              .1
+            learnt_clauses.clauses[start..]
               .iter()
-              .skip(*written - learnt_clauses.2)
-              .filter_map(Weak::upgrade)
+              .filter_map(|(_, weak)| weak.upgrade())
               .map(|inner| ClauseRef { inner }),
           );
-          *written = learnt_clauses.0;
+          *written = learnt_clauses.written;
         },
       }
     });
@@ -107,16 +610,202 @@ impl ClauseDatabase {
     match self.learnt_clauses[id].try_write() {
       Err(_) => {},
       Ok(mut learnt) => {
-        let original = learnt.1.len();
-        learnt.1.retain(|weak| weak.strong_count() > 0);
-        learnt.2 = original - learnt.1.len();
+        let original = learnt.clauses.len();
+        learnt.clauses.retain(|(_, weak)| weak.strong_count() > 0);
+        let deleted = original - learnt.clauses.len();
+        learnt.deleted += deleted;
+        self.num_learnt.fetch_sub(deleted, Ordering::SeqCst);
       },
     };
   }
-  pub fn resize_to(&mut self, n: usize) { self.learnt_clauses.resize_with(n, Default::default); }
+  /// Records the initial clauses and the learnt clauses written by each solver so far, so
+  /// that clauses added afterwards can be dropped by `restore`.
+  /// Each solver's clauses are read in turn, so the checkpoint is only consistent if no solver
+  /// is writing to this database while it is taken.
+  pub fn checkpoint(&self) -> Checkpoint {
+    Checkpoint {
+      initial: self.initial_clauses.len(),
+      written: self
+        .learnt_clauses
+        .iter()
+        .map(|learnt| learnt.read().unwrap().written)
+        .collect(),
+    }
+  }
+  /// Drops the initial and learnt clauses added since cp was taken, and forgets any solution.
+  /// Write indices keep counting up, so readers need not reset the indices they have read up
+  /// to. Preprocessing rewrites the initial clauses, so the checkpoint must be taken after it.
+  /// Solvers keep their own references to learnt clauses, which may depend on dropped
+  /// clauses, so they must be reset before solving again.
+  pub fn restore(&mut self, cp: &Checkpoint) {
+    assert!(
+      cp.initial <= self.initial_clauses.len(),
+      "initial clauses were removed since the checkpoint"
+    );
+    assert_eq!(cp.written.len(), self.num_solvers());
+    self.initial_clauses.truncate(cp.initial);
+    for (learnt, &written) in self.learnt_clauses.iter_mut().zip(cp.written.iter()) {
+      let learnt = learnt.get_mut().unwrap();
+      let start = learnt.clauses.partition_point(|&(idx, _)| idx < written);
+      let dropped = learnt.clauses.len() - start;
+      learnt.clauses.truncate(start);
+      learnt.deleted += dropped;
+      *self.num_learnt.get_mut() -= dropped;
+    }
+    self.clear_solution();
+  }
+  /// Removes the given learnt clauses from every solver's learnt clauses, even if they are
+  /// still alive, so that they are no longer shared or counted towards the learnt clause
+  /// budget. Returns the number of clauses removed.
+  pub fn forget_learnts(&self, clauses: &[ClauseRef]) -> usize {
+    let forgotten = clauses
+      .iter()
+      .map(|cref| Arc::as_ptr(&cref.inner))
+      .collect::<crate::HashSet<_>>();
+    let mut removed = 0;
+    for learnt in self.learnt_clauses.iter() {
+      let mut learnt = learnt.write().unwrap();
+      let original = learnt.clauses.len();
+      learnt
+        .clauses
+        .retain(|(_, weak)| !forgotten.contains(&weak.as_ptr()));
+      let deleted = original - learnt.clauses.len();
+      learnt.deleted += deleted;
+      removed += deleted;
+    }
+    self.num_learnt.fetch_sub(removed, Ordering::SeqCst);
+    removed
+  }
+  /// Makes room for n more solvers, each of which takes one of the new slots when it is created
+  /// with `Solver::with_database`. In deterministic mode every slot must be taken by a solver
+  /// which is solving, as solvers wait for all of them at each rendezvous.
+  pub fn reserve_solvers(&mut self, n: usize) {
+    let solvers = self.num_solvers() + n;
+    self.learnt_clauses.resize_with(solvers, Default::default);
+    self.rendezvous.state.lock().unwrap().parties = solvers;
+  }
+  /// Resizes this database to hold clauses from n solvers, with ids below n considered taken.
+  /// Fails without changing anything if n is less than the current number of solvers, as the
+  /// clauses of solvers with larger ids would be dropped while they still use them.
+  pub fn resize_to(&mut self, n: usize) -> Result<(), TooFewSolvers> {
+    let solvers = self.num_solvers();
+    if n < solvers {
+      return Err(TooFewSolvers {
+        solvers,
+        requested: n,
+      });
+    }
+    self.learnt_clauses.resize_with(n, Default::default);
+    self.rendezvous.state.lock().unwrap().parties = n;
+    let curr_id = self.curr_id.get_mut().unwrap();
+    *curr_id = (*curr_id).max(n);
+    Ok(())
+  }
+}
+
+/// Returns a copy of clause with only the given literals, which are a subset of its own
+fn shrunk(clause: &Clause, literals: Vec<Literal>) -> Arc<Clause> {
+  Arc::new(Clause {
+    literals,
+    initial: clause.initial,
+    activity: clause.activity.clone(),
+    // no longer one of the parsed clauses
+    id: 0,
+    #[cfg(feature = "clause-stats")]
+    propagations: std::sync::atomic::AtomicU64::new(clause.propagations()),
+  })
+}
+
+/// Clauses in a database at some point, which it can be restored to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+  /// number of initial clauses
+  initial: usize,
+  /// write index of each solver's learnt clauses
+  written: Vec<usize>,
+}
+
+/// Size metrics for a clause database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbStats {
+  /// number of initial clauses
+  pub initial: usize,
+  /// number of learnt clauses still alive across all solvers
+  pub live_learnt: usize,
+  /// number of learnt clauses removed from the database by compaction or forgetting them
+  pub deleted_learnt: usize,
+}
+
+impl fmt::Display for DbStats {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "learnt DB: {} live / {} deleted",
+      self.live_learnt, self.deleted_learnt
+    )
+  }
+}
+
+/// Whether the formula of a database was solved by any of the solvers sharing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbSolution {
+  /// No solver has finished yet
+  Undecided,
+  /// The formula has no satisfying assignment
+  Unsat,
+  /// A satisfying assignment of every variable
+  Sat(Vec<bool>),
+}
+
+/// A clause passed to `ClauseDatabase::try_new` contains a variable outside of the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarOutOfRange {
+  /// index of the offending clause
+  pub clause: usize,
+  /// the variable out of range, numbered from 0
+  pub var: usize,
+  pub max_var: usize,
+}
+
+impl fmt::Display for VarOutOfRange {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "clause {} contains variable {} but the database only has {} variables",
+      self.clause,
+      self.var + 1,
+      self.max_var
+    )
+  }
+}
+
+impl std::error::Error for VarOutOfRange {}
+
+/// `ClauseDatabase::resize_to` was asked to hold fewer solvers than it already does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooFewSolvers {
+  /// the number of solvers the database holds
+  pub solvers: usize,
+  /// the number of solvers it was asked to hold
+  pub requested: usize,
 }
 
-#[derive(Debug, Clone, PartialOrd, Ord)]
+impl fmt::Display for TooFewSolvers {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "cannot shrink a database of {} solvers to {}",
+      self.solvers, self.requested
+    )
+  }
+}
+
+impl std::error::Error for TooFewSolvers {}
+
+/// A shared reference to a clause. References are equal only if they refer to the same clause,
+/// so they have no ordering, since ordering by literals would be inconsistent with equality.
+/// Compare the clauses themselves to order by literals.
+#[derive(Debug, Clone)]
 pub struct ClauseRef {
   pub(crate) inner: Arc<Clause>,
 }
@@ -125,13 +814,22 @@ impl PartialEq for ClauseRef {
   fn eq(&self, o: &Self) -> bool { Arc::ptr_eq(&self.inner, &o.inner) }
 }
 impl Eq for ClauseRef {}
+/// Hashes the length and a few literals instead of the whole clause, which is consistent with
+/// pointer equality. Hashing the address would be cheaper, but would make the order of watch
+/// lists depend on where clauses were allocated, so runs would not be reproducible.
 impl Hash for ClauseRef {
-  fn hash<H: Hasher>(&self, state: &mut H) { self.inner.hash(state); }
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    let lits = &self.literals;
+    lits.len().hash(state);
+    for lit in lits.iter().take(2).chain(lits.last()) {
+      lit.hash(state);
+    }
+  }
 }
 
 impl Deref for ClauseRef {
   type Target = Clause;
-  fn deref(&self) -> &Self::Target { &*self.inner }
+  fn deref(&self) -> &Self::Target { &self.inner }
 }
 
 impl From<Clause> for ClauseRef {
@@ -149,6 +847,309 @@ impl ClauseRef {
     lit.assn(assns) == Some(true)
       && causes[lit.var()]
         .as_ref()
-        .map_or(false, |reason| Arc::ptr_eq(&reason.inner, &self.inner))
+        .is_some_and(|reason| Arc::ptr_eq(&reason.inner, &self.inner))
+  }
+  /// Returns the only literal of this clause which is unassigned if every other literal is
+  /// false under assns, meaning that it must be set true.
+  pub fn unit_under(&self, assns: &[Option<bool>]) -> Option<Literal> {
+    let mut unit = None;
+    for &lit in self.literals.iter() {
+      match lit.assn(assns) {
+        Some(false) => {},
+        Some(true) => return None,
+        None if unit.is_some() => return None,
+        None => unit = Some(lit),
+      }
+    }
+    unit
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  #[test]
+  fn tautologies_removed() {
+    let clauses = vec![
+      Clause::from(vec![Literal::from(1), Literal::from(-1), Literal::from(2)]),
+      Clause::from(vec![Literal::from(-2)]),
+    ];
+    let db = ClauseDatabase::new(2, clauses);
+    assert_eq!(db.initial().len(), 1);
+    assert_eq!(db.initial()[0].literals, vec![Literal::from(-2)]);
+  }
+  #[cfg(feature = "clause-stats")]
+  #[test]
+  fn hottest_clauses() {
+    use crate::solver::{SolveResult, Solver};
+    let mut solver = Solver::from_dimacs("src/bin/data/med_unsat/pret60_25.cnf").unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    let hottest = solver.db.hottest_clauses(3);
+    assert_eq!(hottest.len(), 3);
+    assert!(hottest[0].propagations() > 0);
+    assert!(hottest
+      .windows(2)
+      .all(|pair| pair[0].propagations() >= pair[1].propagations()));
+  }
+  #[test]
+  fn var_out_of_range() {
+    let clauses = || {
+      vec![
+        Clause::from(vec![Literal::from(1), Literal::from(2)]),
+        Clause::from(vec![Literal::from(-3), Literal::from(1)]),
+      ]
+    };
+    assert_eq!(
+      ClauseDatabase::try_new(2, clauses()).err(),
+      Some(VarOutOfRange {
+        clause: 1,
+        var: 2,
+        max_var: 2
+      })
+    );
+    assert!(ClauseDatabase::try_new(3, clauses()).is_ok());
+  }
+  #[test]
+  fn clause_ref_identity() {
+    let clause = || ClauseRef::from(Clause::from(vec![Literal::from(1), Literal::from(-2)]));
+    let (a, b) = (clause(), clause());
+    let refs = vec![a.clone(), a, b]
+      .into_iter()
+      .collect::<crate::HashSet<_>>();
+    assert_eq!(refs.len(), 2);
+  }
+  #[test]
+  fn unit_under() {
+    let clause = ClauseRef::from(Clause::from(vec![
+      Literal::from(1),
+      Literal::from(-2),
+      Literal::from(3),
+    ]));
+    assert_eq!(clause.unit_under(&[None, None, None]), None);
+    assert_eq!(
+      clause.unit_under(&[Some(false), Some(true), None]),
+      Some(Literal::from(3))
+    );
+    assert_eq!(clause.unit_under(&[Some(false), None, Some(true)]), None);
+    assert_eq!(
+      clause.unit_under(&[Some(false), Some(true), Some(false)]),
+      None
+    );
+  }
+  #[test]
+  fn shrink_solvers() {
+    let mut db = ClauseDatabase::new(2, vec![Clause::from(vec![Literal::from(1)])]);
+    db.resize_to(3).unwrap();
+    assert_eq!(
+      db.resize_to(2),
+      Err(TooFewSolvers {
+        solvers: 3,
+        requested: 2
+      })
+    );
+    assert_eq!(db.num_solvers(), 3);
+    db.resize_to(3).unwrap();
+    assert_eq!(db.num_solvers(), 3);
+  }
+  #[test]
+  fn learnt_iter() {
+    let mut db = ClauseDatabase::new(2, vec![Clause::from(vec![Literal::from(1)])]);
+    db.resize_to(2).unwrap();
+    let kept = ClauseRef::from(Clause::from(vec![Literal::from(1), Literal::from(2)]));
+    let dropped = ClauseRef::from(Clause::from(vec![Literal::from(1), Literal::from(-2)]));
+    db.add_learnts(0, &mut vec![kept.clone()]);
+    db.add_learnts(1, &mut vec![dropped]);
+    assert_eq!(db.learnt_iter().collect::<Vec<_>>(), vec![kept.clone()]);
+    assert_eq!(db.initial_iter().count(), 1);
+    assert_eq!(db.iter().skip(1).collect::<Vec<_>>(), vec![kept.clone()]);
+    let stats = db.stats();
+    assert_eq!((stats.initial, stats.live_learnt), (1, 1));
+    db.compact(1);
+    assert_eq!(db.stats().deleted_learnt, 1);
+  }
+  #[test]
+  fn checkpoint() {
+    let mut db = ClauseDatabase::new(2, vec![Clause::from(vec![Literal::from(1)])]);
+    let learnt =
+      |lit: i32| ClauseRef::from(Clause::from(vec![Literal::from(lit), Literal::from(2)]));
+    let (kept, dropped) = (learnt(1), learnt(-1));
+    db.add_learnts(0, &mut vec![kept.clone()]);
+    let cp = db.checkpoint();
+    db.add_initial(Clause::from(vec![Literal::from(-1)]));
+    assert_eq!(db.add_learnts(0, &mut vec![dropped.clone()]), 2);
+    db.add_solution(DbSolution::Unsat);
+    db.restore(&cp);
+    assert_eq!(db.initial().len(), 1);
+    // the dropped clause is still alive, but no longer in the database
+    assert_eq!(db.learnt_iter().collect::<Vec<_>>(), vec![kept]);
+    assert!(!dropped.is_empty());
+    assert_eq!(db.get_solution(), DbSolution::Undecided);
+    assert!(!db.terminated());
+    // readers which already read the dropped clause still see later clauses
+    let mut times = vec![2];
+    let later = learnt(2);
+    db.add_learnts(0, &mut vec![later.clone()]);
+    let mut read = vec![];
+    db.since(&mut read, &mut times);
+    assert_eq!(read, vec![later]);
+    assert_eq!(db.stats().deleted_learnt, 1);
+  }
+  #[test]
+  fn terminator() {
+    let mut db = Arc::new(ClauseDatabase::new(
+      1,
+      vec![Clause::from(vec![Literal::from(1)])],
+    ));
+    let terminator = db.terminator();
+    // holding the handle does not share the database
+    assert!(Arc::get_mut(&mut db).is_some());
+    terminator.terminate();
+    assert!(db.terminated());
+    assert!(!db.rendezvous());
+    // unlike terminate, forgetting the solution does not undo it
+    db.clear_solution();
+    assert!(db.terminated());
+    assert!(!db.rendezvous());
+  }
+  #[test]
+  fn learnt_budget() {
+    let db = ClauseDatabase::new(2, vec![Clause::from(vec![Literal::from(1)])]);
+    db.set_max_learnt(1);
+    let learnt =
+      |lit: i32| ClauseRef::from(Clause::from(vec![Literal::from(lit), Literal::from(2)]));
+    let mut batch = vec![learnt(1), learnt(-1)];
+    assert_eq!(db.add_learnts(0, &mut batch), 0);
+    assert!(batch.is_empty());
+    assert!(!db.learnt_over_budget());
+    let kept = learnt(1);
+    assert_eq!(db.add_learnts(0, &mut vec![kept.clone()]), 1);
+    assert!(db.learnt_over_budget());
+    drop(kept);
+    db.compact(0);
+    assert!(!db.learnt_over_budget());
+    // solvers adding at the same time never exceed the budget together
+    let mut db = ClauseDatabase::new(2, vec![Clause::from(vec![Literal::from(1)])]);
+    db.reserve_solvers(7);
+    db.set_max_learnt(100);
+    std::thread::scope(|scope| {
+      for id in 0..8 {
+        let db = &db;
+        scope.spawn(move || {
+          for _ in 0..50 {
+            db.add_learnts(id, &mut vec![learnt(1)]);
+          }
+        });
+      }
+    });
+    let written = db
+      .learnt_clauses
+      .iter()
+      .map(|learnt| learnt.read().unwrap().written)
+      .sum::<usize>();
+    assert_eq!(written, 100);
+  }
+  #[test]
+  fn clause_status_counts() {
+    let clauses = vec![
+      Clause::from(vec![Literal::from(1), Literal::from(2)]),
+      Clause::from(vec![Literal::from(-1), Literal::from(3)]),
+      Clause::from(vec![Literal::from(-2), Literal::from(-3)]),
+    ];
+    let db = ClauseDatabase::new(3, clauses);
+    assert_eq!(db.clause_status_counts(&[None; 3]), (0, 0, 3));
+    assert_eq!(
+      db.clause_status_counts(&[Some(true), None, Some(false)]),
+      (2, 1, 0)
+    );
+    assert_eq!(
+      db.clause_status_counts(&[Some(false), Some(true), None]),
+      (2, 0, 1)
+    );
+  }
+  #[test]
+  fn since_after_compactions() {
+    let db = ClauseDatabase::new(2, vec![Clause::from(vec![Literal::from(1)])]);
+    let learnt =
+      |a: i32, b: i32| ClauseRef::from(Clause::from(vec![Literal::from(a), Literal::from(b)]));
+    let (a, b, c, d) = (learnt(1, 2), learnt(1, -2), learnt(-1, 2), learnt(-1, -2));
+    db.add_learnts(0, &mut vec![a.clone(), b.clone()]);
+    let mut clock = vec![0];
+    let mut read = vec![];
+    db.since(&mut read, &mut clock);
+    assert_eq!(read, vec![a.clone(), b.clone()]);
+    read.clear();
+    drop(a);
+    db.compact(0);
+    db.add_learnts(0, &mut vec![c.clone(), d.clone()]);
+    drop(b);
+    db.compact(0);
+    assert_eq!(db.stats().deleted_learnt, 2);
+    db.since(&mut read, &mut clock);
+    assert_eq!(read, vec![c, d]);
+  }
+  #[test]
+  fn since_interleaved_with_compaction() {
+    let mut db = ClauseDatabase::new(2, vec![Clause::from(vec![Literal::from(1)])]);
+    db.resize_to(2).unwrap();
+    let learnt =
+      |a: i32, b: i32| ClauseRef::from(Clause::from(vec![Literal::from(a), Literal::from(b)]));
+    let (a, b, c, d) = (learnt(1, 2), learnt(1, -2), learnt(-1, 2), learnt(-1, -2));
+    let mut clock = vec![0, 0];
+    let mut read = vec![];
+    db.add_learnts(0, &mut vec![a.clone()]);
+    db.add_learnts(1, &mut vec![b.clone()]);
+    db.since(&mut read, &mut clock);
+    assert_eq!(read, vec![a.clone(), b.clone()]);
+    assert_eq!(clock, vec![1, 1]);
+    read.clear();
+
+    // clauses removed after the reader's position must not shift what it reads next
+    db.add_learnts(0, &mut vec![c.clone(), d.clone()]);
+    drop(c);
+    db.compact(0);
+    db.since(&mut read, &mut clock);
+    assert_eq!(read, vec![d.clone()]);
+    assert_eq!(clock, vec![3, 1]);
+    read.clear();
+
+    // a reader behind a compaction resumes from the first clause it has not read
+    let mut stale = vec![0, 0];
+    drop(a);
+    db.compact(0);
+    db.compact(1);
+    db.since(&mut read, &mut stale);
+    assert_eq!(read, vec![d, b]);
+    assert_eq!(stale, clock);
+  }
+  #[test]
+  fn strengthen() {
+    let lits =
+      |lits: &[i32]| Clause::from(lits.iter().map(|&l| Literal::from(l)).collect::<Vec<_>>());
+    let clauses = vec![lits(&[1, 2]), lits(&[-1, 2, 3]), lits(&[-2, -3])];
+    let mut db = ClauseDatabase::new(3, clauses);
+    assert_eq!(db.strengthen(), 1);
+    assert_eq!(db.initial()[0].literals, lits(&[1, 2]).literals);
+    assert_eq!(db.initial()[1].literals, lits(&[2, 3]).literals);
+    assert_eq!(db.initial()[2].literals, lits(&[-2, -3]).literals);
+  }
+  #[test]
+  fn compact_initial() {
+    use crate::solver::Solver;
+    let clauses = vec![vec![1], vec![-1, 2], vec![-2, 3, 4], vec![5, 6]];
+    let mut solver = Solver::from_clauses(clauses.clone(), 6);
+    assert_eq!(solver.compact_initial(), Some(2));
+    let literals = solver
+      .db
+      .initial()
+      .iter()
+      .map(|clause| clause.literals.clone())
+      .collect::<Vec<_>>();
+    let lits = |lits: &[i32]| lits.iter().map(|&l| Literal::from(l)).collect::<Vec<_>>();
+    assert_eq!(
+      literals,
+      vec![lits(&[3, 4]), lits(&[5, 6]), lits(&[1]), lits(&[2])]
+    );
+    let model = solver.solve().model().unwrap();
+    assert!(Solver::from_clauses(clauses, 6).verify(&model));
   }
 }
@@ -1,23 +1,101 @@
 use crate::{clause::Clause, literal::Literal};
-use std::io;
+use std::{
+  fmt,
+  fs::File,
+  io::{self, BufRead, BufReader, Write},
+  mem,
+};
 
+/// Why a DIMACS file could not be read under strict checking
+#[derive(Debug)]
+pub enum DimacsError {
+  Io(io::Error),
+  /// The number of clauses parsed differs from the number in the "p cnf" header
+  ClauseCountMismatch {
+    declared: usize,
+    parsed: usize,
+  },
+}
+
+impl fmt::Display for DimacsError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Io(err) => write!(f, "{}", err),
+      Self::ClauseCountMismatch { declared, parsed } => write!(
+        f,
+        "DIMACS header declares {} clauses but {} were parsed",
+        declared, parsed
+      ),
+    }
+  }
+}
+
+impl std::error::Error for DimacsError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Self::Io(err) => Some(err),
+      Self::ClauseCountMismatch { .. } => None,
+    }
+  }
+}
+
+impl From<io::Error> for DimacsError {
+  fn from(err: io::Error) -> Self { Self::Io(err) }
+}
+
+/// Reads the clauses and number of variables from a DIMACS file. The number of clauses in the
+/// header is only used as a hint, see `from_dimacs_strict` to check it.
 pub fn from_dimacs<S>(s: S) -> io::Result<(Vec<Clause>, usize)>
 where
   S: AsRef<std::path::Path>, {
-  use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    mem,
+  let (clauses, max_var, _) = parse(BufReader::new(File::open(s)?))?;
+  Ok((clauses, max_var))
+}
+
+/// Reads the clauses and number of variables from a formula in DIMACS format held in memory,
+/// so that small formulas can be written inline.
+pub fn from_str(s: &str) -> io::Result<(Vec<Clause>, usize)> {
+  let (clauses, max_var, _) = parse(s.as_bytes())?;
+  Ok((clauses, max_var))
+}
+
+/// Reads a DIMACS file like `from_dimacs`, but fails if the number of clauses differs from the
+/// number declared in the header, which is often a sign of a truncated or corrupt file.
+pub fn from_dimacs_strict<S>(s: S) -> Result<(Vec<Clause>, usize), DimacsError>
+where
+  S: AsRef<std::path::Path>, {
+  let (clauses, max_var, declared) = parse(BufReader::new(File::open(s)?))?;
+  let declared = declared.unwrap_or(0);
+  if declared != clauses.len() {
+    return Err(DimacsError::ClauseCountMismatch {
+      declared,
+      parsed: clauses.len(),
+    });
+  }
+  Ok((clauses, max_var))
+}
+
+/// Trims whitespace from a line, including the carriage return of Windows line endings, and
+/// the byte order mark some editors write at the start of the first line.
+fn trim_line(line: &str, first: bool) -> &str {
+  let line = if first {
+    line.strip_prefix('\u{feff}').unwrap_or(line)
+  } else {
+    line
   };
-  let file = File::open(s)?;
-  let buf_reader = BufReader::new(file);
+  line.trim()
+}
+
+/// Returns the clauses, number of variables, and number of clauses declared in the header
+fn parse<R: BufRead>(reader: R) -> io::Result<(Vec<Clause>, usize, Option<usize>)> {
   let mut clauses = vec![];
   let mut max_var = 0;
+  let mut declared = None;
   let mut curr_lits = vec![];
   let mut max_seen_var = 0;
-  for line in buf_reader.lines() {
+  for (i, line) in reader.lines().enumerate() {
     let line = line?;
-    let line = line.trim();
+    let line = trim_line(&line, i == 0);
     if line.starts_with('c') {
       continue;
     }
@@ -26,7 +104,9 @@ where
         .split_whitespace()
         .filter_map(|v| v.parse::<usize>().ok());
       max_var = items.next().expect("Missing # variables from \"p cnf\"");
-      clauses.reserve(items.next().expect("Missing # clauses from \"p cnf\""));
+      let num_clauses = items.next().expect("Missing # clauses from \"p cnf\"");
+      clauses.reserve(num_clauses);
+      declared = Some(num_clauses);
     } else {
       line
         .split_whitespace()
@@ -50,11 +130,167 @@ where
         });
     }
   }
-  assert_eq!(
-    max_seen_var, max_var,
+  // variables may be declared without appearing in any clause
+  assert!(
+    max_seen_var <= max_var,
     "DIMAC's file max variable incorrect got {}, expected {}",
-    max_seen_var, max_var
+    max_seen_var,
+    max_var
   );
   clauses.shrink_to_fit();
-  Ok((clauses, max_var))
+  Ok((clauses, max_var, declared))
+}
+
+/// A line of an incremental iCNF file as DIMACS literals, in the order they appear
+#[derive(Debug, PartialEq, Eq)]
+pub enum IcnfLine {
+  /// A clause added to the formula for every following query
+  Clause(Vec<i32>),
+  /// A query of whether the formula so far is satisfiable under these assumptions
+  Assume(Vec<i32>),
+}
+
+/// Reads the clauses and assumption queries of an iCNF file along with the number of variables.
+/// The "p inccnf" header does not declare the number of variables, so it is the largest
+/// variable used.
+pub fn from_icnf<S>(s: S) -> io::Result<(Vec<IcnfLine>, usize)>
+where
+  S: AsRef<std::path::Path>, {
+  parse_icnf(BufReader::new(File::open(s)?))
+}
+
+fn parse_icnf<R: BufRead>(reader: R) -> io::Result<(Vec<IcnfLine>, usize)> {
+  let mut lines = vec![];
+  let mut max_var = 0;
+  let mut curr_lits = vec![];
+  for (i, line) in reader.lines().enumerate() {
+    let line = line?;
+    let line = trim_line(&line, i == 0);
+    if line.starts_with('c') || line.starts_with('p') {
+      continue;
+    }
+    let (assume, line) = match line.strip_prefix('a') {
+      None => (false, line),
+      Some(rest) => (true, rest),
+    };
+    for v in line.split_whitespace() {
+      match v.parse::<i32>().expect("Failed to parse int in icnf file") {
+        0 => {
+          let lits = mem::take(&mut curr_lits);
+          lines.push(if assume {
+            IcnfLine::Assume(lits)
+          } else {
+            IcnfLine::Clause(lits)
+          });
+        },
+        v => {
+          max_var = max_var.max(v.unsigned_abs() as usize);
+          curr_lits.push(v);
+        },
+      }
+    }
+    assert!(
+      !assume || curr_lits.is_empty(),
+      "assumptions must end with 0 on the same line"
+    );
+  }
+  Ok((lines, max_var))
+}
+
+/// Writes model as DIMACS value lines, each starting with `v` and the last ending in 0.
+pub fn write_model<W: Write>(mut w: W, model: &[bool]) -> io::Result<()> {
+  let lits = model
+    .iter()
+    .enumerate()
+    .map(|(var, &val)| {
+      if val {
+        var as i64 + 1
+      } else {
+        -(var as i64 + 1)
+      }
+    })
+    .collect::<Vec<_>>();
+  for line in lits.chunks(10) {
+    write!(w, "v")?;
+    for lit in line {
+      write!(w, " {}", lit)?;
+    }
+    writeln!(w)?;
+  }
+  writeln!(w, "v 0")
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  #[test]
+  fn model_lines() {
+    let mut out = vec![];
+    let model = (0..12).map(|var| var % 3 == 0).collect::<Vec<_>>();
+    write_model(&mut out, &model).unwrap();
+    assert_eq!(
+      String::from_utf8(out).unwrap(),
+      "v 1 -2 -3 4 -5 -6 7 -8 -9 10\nv -11 -12\nv 0\n"
+    );
+  }
+  #[test]
+  fn parse_str() {
+    let (clauses, max_var) = from_str("c inline\np cnf 3 2\n1 -3 0\n2 3 -1 0\n").unwrap();
+    let (expected, expected_max_var) = from_dimacs("src/bin/data/small/sample.cnf").unwrap();
+    assert_eq!(clauses, expected);
+    assert_eq!(max_var, expected_max_var);
+    // clauses may span lines
+    let (clauses, _) = from_str("p cnf 2 1\n1\n-2 0").unwrap();
+    assert_eq!(
+      clauses[0].literals,
+      vec![Literal::from(1), Literal::from(-2)]
+    );
+  }
+  #[test]
+  fn windows_line_endings() {
+    let (expected, _) = from_str("p cnf 3 2\n1 -3 0\n2 3 -1 0\n").unwrap();
+    for formula in &[
+      "\u{feff}p cnf 3 2\r\n1 -3 0\r\n2 3 -1 0\r\n",
+      "\u{feff}c from windows\r\np cnf 3 2\r\n1 -3\r 0\r\n2 3 -1 0",
+    ] {
+      let (clauses, max_var) = from_str(formula).unwrap();
+      assert_eq!(clauses, expected);
+      assert_eq!(max_var, 3);
+    }
+    let (lines, _) = parse_icnf("\u{feff}p inccnf\r\n1 2 0\r\na -1 0\r\n".as_bytes()).unwrap();
+    assert_eq!(
+      lines,
+      vec![IcnfLine::Clause(vec![1, 2]), IcnfLine::Assume(vec![-1])]
+    );
+  }
+  #[test]
+  fn icnf() {
+    let (lines, max_var) =
+      parse_icnf("p inccnf\n1 2 0\nc query\na -1 0\n-2\n3 0\na 0\n".as_bytes()).unwrap();
+    assert_eq!(max_var, 3);
+    assert_eq!(
+      lines,
+      vec![
+        IcnfLine::Clause(vec![1, 2]),
+        IcnfLine::Assume(vec![-1]),
+        IcnfLine::Clause(vec![-2, 3]),
+        IcnfLine::Assume(vec![]),
+      ]
+    );
+  }
+  #[test]
+  fn clause_count_mismatch() {
+    let (clauses, _) = from_dimacs_strict("src/bin/data/small/sample.cnf").unwrap();
+    assert_eq!(clauses.len(), 2);
+    // declares 6 clauses but contains 8
+    let path = "src/bin/data/small/bt_no.cnf";
+    assert!(matches!(
+      from_dimacs_strict(path),
+      Err(DimacsError::ClauseCountMismatch {
+        declared: 6,
+        parsed: 8
+      })
+    ));
+    assert_eq!(from_dimacs(path).unwrap().0.len(), 8);
+  }
 }
@@ -1,12 +1,19 @@
-#![feature(slice_partition_at_index)]
-#![feature(div_duration)]
+pub mod builder;
 mod clause;
 pub mod database;
-mod dimacs;
+pub mod dimacs;
 pub mod literal;
 mod luby;
+pub mod model;
+mod proof;
 mod stats;
 mod var_state;
 mod watch_list;
 
 pub mod solver;
+
+/// Hashes with fixed keys instead of per map random keys, so that iteration order and
+/// therefore the search is the same on every run.
+type FixedState = std::hash::BuildHasherDefault<ahash::AHasher>;
+pub(crate) type HashMap<K, V> = hashbrown::HashMap<K, V, FixedState>;
+pub(crate) type HashSet<T> = hashbrown::HashSet<T, FixedState>;
@@ -51,7 +51,7 @@ impl From<i32> for Literal {
   #[inline]
   fn from(i: i32) -> Self {
     debug_assert_ne!(i, 0);
-    Literal::new((i.abs() as u32) - 1, i < 0)
+    Literal::new(i.unsigned_abs() - 1, i < 0)
   }
 }
 
@@ -60,6 +60,12 @@ impl From<u32> for Literal {
   fn from(u: u32) -> Self { Literal(u) }
 }
 
+impl Display for Literal {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}{}", if self.negated() { "!" } else { "" }, self.var())
+  }
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -69,16 +75,10 @@ mod test {
       let lit = Literal::from(-var);
       assert_eq!(lit.var(), (var - 1) as usize);
       assert!(lit.negated());
-      assert_eq!(lit.val(), false);
+      assert!(!lit.val());
       assert_eq!((!lit).var(), (var - 1) as usize);
       assert!(!(!lit).negated());
-      assert_eq!((!lit).val(), true);
+      assert!((!lit).val());
     });
   }
 }
-
-impl Display for Literal {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(f, "{}{}", if self.negated() { "!" } else { "" }, self.var())
-  }
-}
@@ -34,17 +34,47 @@ impl RestartState {
       base_restart_interval: base,
       restart_inc_interval: inc,
       num_restarts: 0,
-      remaining: base * luby(inc, 0),
+      remaining: base * luby(0, inc),
     }
   }
+  /// Returns the index in the luby sequence of the current restart interval, which is the
+  /// number of previous restarts
+  pub fn luby_index(&self) -> u64 { self.num_restarts }
+  /// Returns the number of conflicts in the current restart interval
+  pub fn threshold(&self) -> u64 {
+    luby(self.num_restarts, self.restart_inc_interval) * self.base_restart_interval
+  }
+  /// Returns the number of conflicts remaining until a restart is suggested
+  pub fn remaining(&self) -> u64 { self.remaining }
   #[inline]
   pub fn notify_conflict(&mut self) { self.remaining = self.remaining.saturating_sub(1); }
   #[inline]
   pub fn restart_suggested(&self) -> bool { self.remaining == 0 }
   pub fn restart(&mut self) {
     self.num_restarts += 1;
-    let new_remaining =
-      luby(self.restart_inc_interval, self.num_restarts) * self.base_restart_interval;
-    self.remaining = new_remaining;
+    self.remaining = self.threshold();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::solver::{RESTART_BASE, RESTART_INC};
+  #[test]
+  fn luby_schedule() {
+    let mut state = RestartState::new(RESTART_BASE, RESTART_INC);
+    let mut intervals = vec![];
+    for _ in 0..7 {
+      assert_eq!(state.remaining(), state.threshold());
+      let mut conflicts = 0;
+      while !state.restart_suggested() {
+        state.notify_conflict();
+        conflicts += 1;
+      }
+      intervals.push(conflicts / RESTART_BASE);
+      state.restart();
+    }
+    assert_eq!(intervals, vec![1, 1, 2, 1, 1, 2, 4]);
+    assert_eq!(state.luby_index(), 7);
   }
 }
@@ -0,0 +1,43 @@
+use crate::clause::Clause;
+use std::sync::Arc;
+
+/// Rules for assigning variables which were removed from the formula, so that a model of the
+/// remaining formula can be extended to a model of the original one.
+#[derive(Debug, Clone, Default)]
+pub struct ModelExtension {
+  /// Removed variables in order of removal, along with the clauses that were removed with them
+  rules: Vec<(usize, Vec<Arc<Clause>>)>,
+}
+
+impl ModelExtension {
+  /// Records that var was removed along with clauses, which it must satisfy when reconstructed
+  pub(crate) fn push(&mut self, var: usize, clauses: Vec<Arc<Clause>>) {
+    self.rules.push((var, clauses));
+  }
+  /// returns the number of variables removed
+  pub fn len(&self) -> usize { self.rules.len() }
+  pub fn is_empty(&self) -> bool { self.rules.is_empty() }
+  /// Iterates over the removed variables in order of removal
+  pub fn vars(&self) -> impl Iterator<Item = usize> + '_ { self.rules.iter().map(|&(var, _)| var) }
+  /// Iterates over every clause removed along with some variable
+  pub fn clauses(&self) -> impl Iterator<Item = &Arc<Clause>> + '_ {
+    self.rules.iter().flat_map(|(_, clauses)| clauses.iter())
+  }
+  /// Assigns removed variables in assns in reverse order of removal, so that the clauses
+  /// removed with each are satisfied.
+  /// Relies on assns satisfying the formula that remained after all removals.
+  pub fn extend(&self, assns: &mut [Option<bool>]) {
+    for (var, clauses) in self.rules.iter().rev() {
+      assns[*var] = Some(false);
+      let unsat = clauses.iter().any(|clause| {
+        !clause
+          .literals
+          .iter()
+          .any(|lit| lit.assn(assns) == Some(true))
+      });
+      if unsat {
+        assns[*var] = Some(true);
+      }
+    }
+  }
+}
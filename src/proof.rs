@@ -0,0 +1,90 @@
+use crate::{literal::Literal, HashMap, HashSet};
+use std::{
+  fmt,
+  io::{self, Write},
+};
+
+/// Writes clauses derived by solvers as an LRAT proof, where each line is
+/// `id literals 0 hints 0` and hints are the ids of clauses which, in order, become unit or
+/// conflicting once every literal of the derived clause is false.
+pub(crate) struct Lrat {
+  out: Box<dyn Write + Send>,
+  /// The first error from writing, after which nothing else is written
+  error: Option<io::Error>,
+}
+
+impl Lrat {
+  /// Creates a proof written to out. Clauses are numbered by the solvers deriving them.
+  pub fn new(out: Box<dyn Write + Send>) -> Self { Self { out, error: None } }
+  /// Adds a clause with the given id, implied by hints, to the proof
+  pub fn add(&mut self, id: u64, literals: &[Literal], hints: &[u64]) {
+    if self.error.is_none() {
+      self.error = self.write_line(id, literals, hints).err();
+    }
+  }
+  fn write_line(&mut self, id: u64, literals: &[Literal], hints: &[u64]) -> io::Result<()> {
+    write!(self.out, "{}", id)?;
+    for &lit in literals {
+      let var = lit.var() as i64 + 1;
+      write!(self.out, " {}", if lit.negated() { -var } else { var })?;
+    }
+    write!(self.out, " 0")?;
+    for hint in hints {
+      write!(self.out, " {}", hint)?;
+    }
+    writeln!(self.out, " 0")
+  }
+  /// Flushes the proof, returning the first error that occurred while writing it
+  pub fn finish(&mut self) -> io::Result<()> {
+    match self.error.take() {
+      Some(e) => Err(e),
+      None => self.out.flush(),
+    }
+  }
+}
+
+impl fmt::Debug for Lrat {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Lrat").finish_non_exhaustive()
+  }
+}
+
+/// Records which clauses each derived clause was derived from, so that the initial clauses a
+/// refutation depends on can be found.
+#[derive(Debug, Default)]
+pub(crate) struct Derivations {
+  /// ids of the clauses each derived clause was derived from, as in an LRAT proof
+  hints: HashMap<u64, Vec<u64>>,
+  /// id of the empty clause, once it is derived
+  empty: Option<u64>,
+}
+
+impl Derivations {
+  /// Records that the clause with the given id and literals was derived from hints
+  pub fn add(&mut self, id: u64, literals: &[Literal], hints: Vec<u64>) {
+    if literals.is_empty() {
+      self.empty = Some(id);
+    }
+    self.hints.insert(id, hints);
+  }
+  /// Returns the ids of the clauses which were not derived, but which the empty clause was
+  /// derived from, or none if the empty clause was not derived.
+  /// Clauses without an id are left out.
+  pub fn core(&self) -> Option<HashSet<u64>> {
+    let mut seen = HashSet::default();
+    let mut core = HashSet::default();
+    let mut stack = vec![self.empty?];
+    while let Some(id) = stack.pop() {
+      if id == 0 || !seen.insert(id) {
+        continue;
+      }
+      match self.hints.get(&id) {
+        Some(hints) => stack.extend(hints.iter().copied()),
+        None => {
+          core.insert(id);
+        },
+      }
+    }
+    Some(core)
+  }
+}
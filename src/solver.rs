@@ -1,19 +1,170 @@
 use crate::{
+  builder::SolverBuilder,
   clause::Clause,
-  database::{ClauseDatabase, ClauseRef},
+  database::{ClauseDatabase, ClauseRef, DbSolution},
   literal::Literal,
-  luby::RestartState,
-  stats::{Record, Stats},
+  proof::{Derivations, Lrat},
+  stats::Record,
   var_state::VariableState,
   watch_list::WatchList,
+  HashMap, HashSet,
+};
+pub use crate::{
+  luby::RestartState,
+  stats::{Stats, StatsSnapshot},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+  cell::RefCell,
+  fmt,
+  io::{self, Write},
+  sync::{Arc, Mutex, Weak},
 };
-use hashbrown::HashMap;
-use std::{cell::RefCell, sync::Arc};
 
 pub const RESTART_BASE: u64 = 100;
 pub const RESTART_INC: u64 = 2;
 pub const LEARNTSIZE_FACTOR: f64 = 1.0 / 3.0;
 pub const LEARNTSIZE_INC: f64 = 1.3;
+/// Fraction of learnt clauses kept each time the learnt clauses are cleaned
+pub const CLAUSE_KEEP: f64 = 0.5;
+/// Amount a clause's activity is increased by each time it is used in conflict analysis
+pub const CLAUSE_BUMP: u64 = 1 << 10;
+/// Fraction of its activity every learnt clause keeps each time learnt clauses are cleaned
+pub const CLAUSE_DECAY: f64 = 0.5;
+/// Phases saved phases are reset to in turn when rephasing
+pub const REPHASE_POLICY: &[Phase] = &[Phase::True, Phase::False, Phase::Random];
+
+/// The polarity each variable is first decided with, before phase saving takes over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+  False,
+  True,
+  /// Picked by the solver's seeded random number generator, and picked again on every
+  /// decision if phase saving is disabled
+  Random,
+}
+
+/// Which two literals of each clause are watched when a watch list is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchSelection {
+  /// The first two literals, which are the smallest
+  First,
+  /// The two literals whose variables have the lowest activity, which are likely to be
+  /// assigned late, so the clause is visited less often while propagating
+  LowestActivity,
+}
+
+/// When the least active learnt clauses are deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReducePolicy {
+  /// Whenever more clauses were learnt or transferred than a limit, which starts at
+  /// `learntsize_factor` times the number of initial clauses and grows by `learntsize_inc`
+  /// each time
+  SizeBudget,
+  /// Every given number of conflicts, however many clauses were learnt, which must be positive
+  FixedInterval(u64),
+}
+
+/// Per-solver search parameters.
+/// Replicas sharing a database may each use a different configuration, see
+/// `Solver::replicate_diverse`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverConfig {
+  /// number of conflicts in the first restart interval
+  pub restart_base: u64,
+  /// base of the luby sequence scaling each restart interval
+  pub restart_inc: u64,
+  /// initial limit of learnt clauses as a fraction of the number of initial clauses
+  pub learntsize_factor: f64,
+  /// growth of the learnt clause limit each time learnt clauses are cleaned
+  pub learntsize_inc: f64,
+  /// rate variable activities are divided by after each conflict
+  pub var_decay: f32,
+  /// initial polarity of every variable
+  pub phase: Phase,
+  /// whether variables are decided with the polarity they last had
+  pub phase_saving: bool,
+  /// seed for this solver's random number generator
+  pub seed: u64,
+  /// fraction of learnt clauses, by activity, kept each time learnt clauses are cleaned
+  pub clause_keep: f64,
+  /// amount a clause's activity is increased by each time it is used in conflict analysis
+  pub clause_bump: u64,
+  /// fraction of its activity every remaining learnt clause keeps each time learnt clauses are
+  /// cleaned, so that recently used clauses are favored over ones used often long ago.
+  /// Activities are shared with any solver the clause was transferred to, each of which decays
+  /// it when it cleans.
+  pub clause_decay: f64,
+  /// whether to check that learnt clauses are not tautologies, dropping any that are.
+  /// Always checked with debug assertions.
+  pub check_learnts: bool,
+  /// most restarts performed until the solver is reset, or none for no limit
+  pub max_restarts: Option<u32>,
+  /// number of restarts between resets of the saved phases, or none to never reset them.
+  /// Rephasing lets phase saving escape from an assignment it keeps returning to.
+  pub rephase_interval: Option<u32>,
+  /// phases the saved phases are reset to, each rephase using the next in turn
+  pub rephase_policy: &'static [Phase],
+  /// conflicts between exchanges of learnt clauses in deterministic mode, or none to exchange
+  /// them whenever possible.
+  /// In deterministic mode solvers sharing a database exchange clauses at a rendezvous of all
+  /// of them, so that given the same seeds a parallel run finds the same solution every time.
+  /// It must be set for every solver sharing the database, all of which must be solving at the
+  /// same time. A learnt clause budget set on the database is not deterministic.
+  pub deterministic: Option<u64>,
+  /// whether learnt clauses are shared with other solvers through the database. Without
+  /// sharing, each solver keeps its learnt clauses in its own watch list, so replicas search
+  /// independently as a pure portfolio. In deterministic mode solvers still meet at every
+  /// rendezvous, which keeps the solution found reproducible.
+  pub share_clauses: bool,
+  /// whether learnt clauses are kept. Without learning, each learnt clause is only the reason
+  /// for its asserting literal until it is backtracked, so the search is DPLL with backjumping.
+  /// Restarts would then lose all progress, so they are not performed.
+  pub learning: bool,
+  /// which literals each clause watches when watch lists are built, either when the solver is
+  /// created or when the formula changes
+  pub watch_selection: WatchSelection,
+  /// most literals in a learnt clause which is kept, or none for no limit. Longer clauses are
+  /// only used to backjump, as without learning, which bounds the memory used by learnt
+  /// clauses.
+  pub max_learnt_len: Option<usize>,
+  /// whether every learnt clause is kept for the whole search. Clauses are then never
+  /// deleted for being inactive or satisfied at level 0, so the learnt clauses only depend on
+  /// the conflicts, at the cost of slower propagation as they accumulate.
+  pub keep_all_learnt: bool,
+  /// when learnt clauses are deleted. Either way they are also deleted whenever the database
+  /// holds more learnt clauses than its limit.
+  pub reduce_policy: ReducePolicy,
+}
+
+impl Default for SolverConfig {
+  fn default() -> Self {
+    Self {
+      restart_base: RESTART_BASE,
+      restart_inc: RESTART_INC,
+      learntsize_factor: LEARNTSIZE_FACTOR,
+      learntsize_inc: LEARNTSIZE_INC,
+      var_decay: crate::var_state::DEFAULT_DECAY_RATE,
+      phase: Phase::False,
+      phase_saving: true,
+      seed: 0,
+      clause_keep: CLAUSE_KEEP,
+      clause_bump: CLAUSE_BUMP,
+      clause_decay: CLAUSE_DECAY,
+      check_learnts: false,
+      max_restarts: None,
+      rephase_interval: None,
+      rephase_policy: REPHASE_POLICY,
+      deterministic: None,
+      share_clauses: true,
+      learning: true,
+      watch_selection: WatchSelection::First,
+      max_learnt_len: None,
+      keep_all_learnt: false,
+      reduce_policy: ReducePolicy::SizeBudget,
+    }
+  }
+}
 
 #[derive(Clone, Debug)]
 pub struct Solver {
@@ -23,6 +174,9 @@ pub struct Solver {
   /// which vars are assigned to what at the current stage
   assignments: Vec<Option<bool>>,
 
+  /// number of variables which are neither assigned nor eliminated
+  free_vars: usize,
+
   /// stack of assignments, needed for backtracking
   assignment_trail: Vec<Literal>,
 
@@ -49,6 +203,36 @@ pub struct Solver {
   /// vector clock of clauses for database
   latest_clauses: Vec<usize>,
 
+  /// learnt clauses not yet written to the database
+  to_write: Vec<ClauseRef>,
+
+  /// number of exchanges in deterministic mode
+  exchanges: u64,
+
+  /// literals assumed at the start of every solve, in the order they were pushed
+  assumptions: Vec<Literal>,
+
+  /// highest level holding an assumption during a solve, which is never backtracked below
+  frontier: usize,
+
+  /// assumptions which the last solve showed cannot all hold
+  failed: Vec<Literal>,
+
+  /// level 0 assignments chosen by `eliminate_pure_literals`, which unlike units are not
+  /// implied by the formula
+  pure_literals: Vec<Literal>,
+
+  /// id of the next clause derived by this solver
+  next_id: u64,
+
+  /// literal to assign on the next propagation, along with its cause if it is not a decision
+  pending: Option<(Literal, Option<ClauseRef>)>,
+
+  /// clauses transferred from other solvers, keyed by their address.
+  /// Holding the weak reference keeps the allocation alive, so an address cannot be reused by
+  /// another clause while it is in this map.
+  imported: HashMap<usize, Weak<Clause>>,
+
   /// which level is this solver currently at
   level: usize,
 
@@ -58,104 +242,416 @@ pub struct Solver {
   /// Restart State using Luby
   restart_state: RestartState,
 
-  // a reusable stack from lit redundant
-  // should be clear before and after each call to lit redundant
-  analyze_stack: RefCell<Vec<Literal>>,
-
   // a reusable tracker for what was seen and what was not
   // should be clear before and after each call to analyze
   analyze_seen: RefCell<HashMap<usize, SeenState>>,
 
+  /// Search parameters for this solver
+  config: SolverConfig,
+
+  /// Source of randomness, seeded from the config so runs are reproducible
+  rng: StdRng,
+
+  /// Optional hook called periodically while solving
+  progress: Option<Progress>,
+
+  /// Optional LRAT proof of learnt clauses, shared with replicas so that ids stay unique
+  lrat: Option<Arc<Mutex<Lrat>>>,
+  /// what each derived clause was derived from, if the UNSAT core is tracked
+  derivations: Option<Arc<Mutex<Derivations>>>,
+  /// whether the last solve found its result itself, instead of another solver sharing the
+  /// database
+  found_by_self: bool,
+
   /// Statistics for this solver
   pub stats: Stats,
 }
 
+/// Called with a solver's statistics to report its progress
+pub type ProgressHook = Box<dyn FnMut(&Stats) + Send>;
+
+/// A hook called with a solver's statistics every so many conflicts.
+/// Replicas share the hook of the solver they were replicated from.
+#[derive(Clone)]
+struct Progress {
+  every_conflicts: u64,
+  hook: Arc<Mutex<ProgressHook>>,
+}
+
+impl fmt::Debug for Progress {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Progress")
+      .field("every_conflicts", &self.every_conflicts)
+      .finish_non_exhaustive()
+  }
+}
+
 impl Solver {
-  /// Attempt to find a satisfying assignment for the current solver
-  pub fn solve(&mut self) -> Option<Vec<bool>> {
+  /// Attempt to find a satisfying assignment for the current solver.
+  /// Returns unknown if the database was terminated before any solver sharing it finished.
+  /// If assumptions were pushed, only finds assignments satisfying all of them, and returns
+  /// UNSAT if there are none, which is not recorded in the database.
+  pub fn solve(&mut self) -> SolveResult {
+    self.found_by_self = false;
+    let result = self.search();
+    // assumptions only hold while searching, so clear them unless they are part of a model
+    if std::mem::take(&mut self.frontier) > 0 && !matches!(result, SolveResult::Sat(_)) {
+      self.pending = None;
+      self.backtrack_to(0);
+    }
+    if self.config.deterministic.is_none() {
+      return result;
+    }
+    // Other solvers may still find a solution which takes precedence
+    self.db.leave();
+    match self.finished() {
+      None => result,
+      Some(shared) => {
+        self.found_by_self &= self.db.solved_by() == Some(self.id);
+        shared
+      },
+    }
+  }
+  /// Returns true if the result of the last solve was found by this solver, or false if it
+  /// stopped because another solver sharing the database found one first, or it was stopped
+  /// without a result.
+  pub fn found_by_self(&self) -> bool { self.found_by_self }
+  /// Solves like `solve`, also returning a snapshot of this solver's statistics once it is
+  /// done.
+  pub fn run(&mut self) -> SolveReport {
+    let result = self.solve();
+    SolveReport {
+      result,
+      stats: self.stats,
+      found_by_self: self.found_by_self,
+    }
+  }
+  fn search(&mut self) -> SolveResult {
+    if let Some(result) = self.finished() {
+      return result;
+    }
+    // start together, so that none finish before another has started
+    if self.config.deterministic.is_some() && !self.db.rendezvous() {
+      return self.finished().unwrap_or(SolveResult::Unknown);
+    }
     assert_eq!(self.level, 0);
+    self.failed.clear();
+    if !self.assume() {
+      self.found_by_self = true;
+      return SolveResult::Unsat;
+    }
+    // Any assignment satisfies a formula without clauses, so skip searching entirely
+    if self.db.initial().is_empty() {
+      let assns = self
+        .assignments
+        .iter()
+        .zip(self.polarities.iter())
+        .map(|(assn, &polarity)| Some(assn.unwrap_or(polarity)))
+        .collect::<Vec<_>>();
+      let solution = self.reconstruct_model(&assns);
+      self.add_solution(DbSolution::Sat(solution.clone()));
+      return SolveResult::Sat(solution);
+    }
     let mut unsolved_buffer = vec![];
-    let mut to_write_buffer = vec![];
-    let mut max_learnts = (self.db.initial().len() as f64) * LEARNTSIZE_FACTOR;
+    let mut max_learnts = (self.db.initial().len() as f64) * self.config.learntsize_factor;
+    let reduce_interval = match self.config.reduce_policy {
+      ReducePolicy::SizeBudget => None,
+      ReducePolicy::FixedInterval(interval) => Some(interval as usize),
+    };
+    let mut next_reduce = self
+      .stats
+      .clauses_learned
+      .saturating_add(reduce_interval.unwrap_or(0));
 
-    while self.has_unassigned_vars() {
-      self.next_level();
-      let lit = self.choose_lit();
-      let mut conflict = self.with(lit, None);
+    loop {
+      if let Some(result) = self.interrupted() {
+        return result;
+      }
+      // every variable is assigned without conflict, so the formula is satisfied
+      if self.decide().is_none() {
+        break;
+      }
+      let mut conflict = self.propagate();
       while let Some(clause) = conflict {
-        self.restart_state.notify_conflict();
-        if self.level == 0 {
-          self.db.add_solution(None);
-          return None;
+        if let Some(result) = self.interrupted() {
+          return result;
         }
-        if let Some(sol) = self.db.get_solution() {
-          return sol;
+        // a conflict without any decisions past the assumptions refutes them
+        if self.frontier > 0 && self.level <= self.frontier {
+          self.failed = self.assumptions_implying(clause.literals.iter().map(|lit| lit.var()));
+          self.found_by_self = true;
+          return SolveResult::Unsat;
         }
-        self.stats.record(Record::LearnedClause);
-        let (learnt_clause, backtrack_lvl) = self.analyze(&clause, self.level);
-        assert!(backtrack_lvl < self.level);
-        self.backtrack_to(backtrack_lvl);
-        if learnt_clause.is_empty() {
-          return None;
+        if !self.analyze_and_backtrack(&clause) {
+          return SolveResult::Unsat;
         }
-        self
-          .stats
-          .record(Record::LearntLiterals(learnt_clause.literals.len()));
-        let cref = ClauseRef::from(learnt_clause);
-        to_write_buffer.push(cref.clone());
-        let lit = self.watch_list.add_learnt(&self.assignments, &cref);
-
-        self.var_state.decay();
-        // self.watch_list.clause_decay();
-
-        // assign resulting literal with the learnt clause as the cause
-        conflict = self.with(lit, Some(cref));
-        assert_eq!(self.assignments[lit.var()], Some(lit.val()));
-
+        conflict = self.propagate();
         // handle transfers when there are no more conflicts in own clauses
         // but might need to handle conflicts here
-        if conflict == None {
-          self
-            .stats
-            .record(Record::Written(to_write_buffer.len() as u32));
-          self.latest_clauses[self.id] = self.db.add_learnts(self.id, &mut to_write_buffer);
-          assert!(to_write_buffer.is_empty());
-          let original_len = unsolved_buffer.len();
-          self
-            .db
-            .since(&mut unsolved_buffer, &mut self.latest_clauses);
-          self
-            .stats
-            .record(Record::Transferred(unsolved_buffer.len() - original_len));
-          // TODO need to make it so that can add more than one transfer at the same time?
-          while let Some(transfer) = unsolved_buffer.pop() {
-            if let Some(sol) = self.db.get_solution() {
-              return sol;
-            }
-            conflict = self.add_transfer(transfer);
-            if conflict.is_some() {
-              break;
-            }
-          }
+        if conflict.is_none() {
+          conflict = match self.config.deterministic {
+            None if !self.config.share_clauses => None,
+            None => self.exchange(&mut unsolved_buffer),
+            Some(interval) => match self.exchange_at_rendezvous(interval, &mut unsolved_buffer) {
+              Ok(conflict) => conflict,
+              Err(result) => return result,
+            },
+          };
         }
       }
-      if self.restart_state.restart_suggested() {
+      let may_restart = self.config.learning
+        && self
+          .config
+          .max_restarts
+          .is_none_or(|max| self.stats.restarts < max);
+      if may_restart && self.restart_state.restart_suggested() {
         self.stats.record(Record::Restart);
         self.restart_state.restart();
         self.backtrack_to(0);
+        self.maybe_rephase();
+      }
+      if self.config.keep_all_learnt {
+        continue;
       }
       if self.level == 0 {
-        self.watch_list.remove_satisfied(&self.assignments);
+        self.remove_satisfied();
       }
       self.db.compact(self.id);
-      if self.stats.clauses_learned + self.stats.transferred_clauses > (max_learnts as usize) {
-        self.watch_list.clean(&self.assignments, &self.causes);
-        max_learnts *= LEARNTSIZE_INC;
+      let over_budget = self.db.learnt_over_budget();
+      let num_learnts = self.stats.clauses_learned + self.stats.transferred_clauses;
+      let reduce = match reduce_interval {
+        None => num_learnts > (max_learnts as usize),
+        Some(_) => self.stats.clauses_learned >= next_reduce,
+      };
+      if over_budget || reduce {
+        self.watch_list.clean(
+          &self.assignments,
+          &self.causes,
+          self.config.clause_keep,
+          self.config.clause_decay,
+        );
+        self.imported.retain(|_, clause| clause.strong_count() > 0);
+        match reduce_interval {
+          None if !over_budget => max_learnts *= self.config.learntsize_inc,
+          None => {},
+          Some(interval) => next_reduce = self.stats.clauses_learned.saturating_add(interval),
+        }
       }
     }
     let solution = self.final_assignments();
-    self.db.add_solution(Some(solution.clone()));
-    Some(solution)
+    self.add_solution(DbSolution::Sat(solution.clone()));
+    SolveResult::Sat(solution)
+  }
+
+  /// Makes a new decision at a new level, which is assigned by the next call to propagate.
+  /// Returns none without deciding if every variable is already assigned or eliminated.
+  pub fn decide(&mut self) -> Option<Literal> {
+    assert!(
+      self.pending.is_none(),
+      "Must propagate before deciding again"
+    );
+    let lit = self.choose_lit()?;
+    self.next_level();
+    self.pending = Some((lit, None));
+    Some(lit)
+  }
+  /// Assigns the literal from the last decision or learnt clause and propagates it, returning a
+  /// conflicting clause if one is found. Does nothing if there is no such literal.
+  pub fn propagate(&mut self) -> Option<ClauseRef> {
+    let (lit, cause) = self.pending.take()?;
+    self.with(lit, cause)
+  }
+  /// Learns a clause from the conflict and backtracks so that it becomes unit, leaving its
+  /// remaining literal to be assigned by the next call to propagate. Without learning, or if
+  /// the clause is longer than the most literals kept, it is only kept as the reason for that
+  /// literal.
+  /// Returns false if the conflict shows that the formula is UNSAT.
+  pub fn analyze_and_backtrack(&mut self, conflict: &ClauseRef) -> bool {
+    self.restart_state.notify_conflict();
+    self.stats.record(Record::LearnedClause);
+    self.report_progress();
+    let (mut learnt_clause, backtrack_lvl) = match self.analyze(conflict) {
+      // resolution derived the empty clause, so no assignment can satisfy the formula
+      None => {
+        self.prove(&[], conflict);
+        self.add_solution(DbSolution::Unsat);
+        return false;
+      },
+      Some(learnt) => learnt,
+    };
+    debug_assert!(
+      !learnt_clause.is_tautology(),
+      "Learnt a tautology {}",
+      learnt_clause
+    );
+    // a tautology is always satisfied so it cannot assert anything, and only means that
+    // analysis went wrong
+    if self.config.check_learnts && learnt_clause.is_tautology() {
+      self.backtrack_to(backtrack_lvl);
+      return true;
+    }
+    learnt_clause.id = self.prove(&learnt_clause.literals, conflict);
+    let lbd = self.lbd(&learnt_clause.literals);
+    // Every literal of the conflict is assigned at or below the current level, and analysis
+    // resolves until exactly one literal of the learnt clause is at the current level, so the
+    // second highest level is always below it. If this fails, the conflict had no literal at
+    // the current level, meaning it was falsified earlier without being noticed.
+    assert!(
+      backtrack_lvl < self.level,
+      "learnt clause {} does not assert a literal, as its backtrack level {} is not below the \
+       conflict level {}",
+      learnt_clause,
+      backtrack_lvl,
+      self.level
+    );
+    self.backtrack_to(backtrack_lvl);
+    self
+      .stats
+      .record(Record::LearntLiterals(learnt_clause.literals.len(), lbd));
+    let cref = ClauseRef::from(learnt_clause);
+    let too_long = self
+      .config
+      .max_learnt_len
+      .is_some_and(|max| cref.literals.len() > max);
+    if !self.config.learning || too_long {
+      let lit = *cref
+        .literals
+        .iter()
+        .find(|lit| lit.assn(&self.assignments).is_none())
+        .unwrap();
+      self.var_state.decay();
+      self.pending = Some((lit, Some(cref)));
+      return true;
+    }
+    if self.config.share_clauses {
+      self.to_write.push(cref.clone());
+    }
+    let lit = self.watch_list.add_learnt(&self.assignments, &cref);
+
+    self.var_state.decay();
+    // self.watch_list.clause_decay();
+
+    // assign resulting literal with the learnt clause as the cause
+    self.pending = Some((lit, Some(cref)));
+    true
+  }
+  /// Writes clauses learnt since the last exchange to the database and adds clauses written by
+  /// other solvers, returning a conflict if one of them causes one.
+  fn exchange(&mut self, unsolved_buffer: &mut Vec<ClauseRef>) -> Option<ClauseRef> {
+    self
+      .stats
+      .record(Record::Written(self.to_write.len() as u32));
+    self.latest_clauses[self.id] = self.db.add_learnts(self.id, &mut self.to_write);
+    assert!(self.to_write.is_empty());
+    let original_len = unsolved_buffer.len();
+    self.db.since(unsolved_buffer, &mut self.latest_clauses);
+    self
+      .stats
+      .record(Record::Transferred(unsolved_buffer.len() - original_len));
+    // TODO need to make it so that can add more than one transfer at the same time?
+    while let Some(transfer) = unsolved_buffer.pop() {
+      if self.db.terminated() {
+        return None;
+      }
+      let conflict = self.import(transfer);
+      if conflict.is_some() {
+        return conflict;
+      }
+    }
+    None
+  }
+  /// Returns the number of distinct decision levels the given assigned literals are at
+  fn lbd(&self, literals: &[Literal]) -> usize {
+    let mut levels = literals
+      .iter()
+      .filter_map(|lit| self.levels[lit.var()])
+      .collect::<Vec<_>>();
+    levels.sort_unstable();
+    levels.dedup();
+    levels.len()
+  }
+  /// Exchanges clauses with every other solver sharing the database at a rendezvous once
+  /// every interval conflicts, so that which clauses are read does not depend on thread timing.
+  /// Returns the result to stop with if solvers were told to stop.
+  fn exchange_at_rendezvous(
+    &mut self,
+    interval: u64,
+    unsolved_buffer: &mut Vec<ClauseRef>,
+  ) -> Result<Option<ClauseRef>, SolveResult> {
+    if self.stats.clauses_learned as u64 >= (self.exchanges + 1) * interval {
+      self.exchanges += 1;
+      self
+        .stats
+        .record(Record::Written(self.to_write.len() as u32));
+      self.latest_clauses[self.id] = self.db.add_learnts(self.id, &mut self.to_write);
+      // Every solver must write before any reads, and read before any frees clauses again
+      if !self.db.rendezvous() {
+        return Err(self.finished().unwrap_or(SolveResult::Unknown));
+      }
+      let original_len = unsolved_buffer.len();
+      self.db.since(unsolved_buffer, &mut self.latest_clauses);
+      self
+        .stats
+        .record(Record::Transferred(unsolved_buffer.len() - original_len));
+      if !self.db.rendezvous() {
+        return Err(self.finished().unwrap_or(SolveResult::Unknown));
+      }
+    }
+    while let Some(transfer) = unsolved_buffer.pop() {
+      let conflict = self.import(transfer);
+      if conflict.is_some() {
+        return Ok(conflict);
+      }
+    }
+    Ok(None)
+  }
+  /// Returns the result if any solver sharing this database has finished or it was terminated.
+  #[inline]
+  fn finished(&self) -> Option<SolveResult> {
+    if !self.db.terminated() {
+      return None;
+    }
+    Some(SolveResult::from(self.db.get_solution()))
+  }
+  /// Returns the result to stop searching with if any solver sharing this database has
+  /// finished or it was terminated. In deterministic mode that is only checked at a
+  /// rendezvous, as when it happens depends on thread timing.
+  #[inline]
+  fn interrupted(&self) -> Option<SolveResult> {
+    if self.config.deterministic.is_some() {
+      return None;
+    }
+    self.finished()
+  }
+  /// Adds a solution found by this solver to the database.
+  fn add_solution(&mut self, sol: DbSolution) {
+    self.found_by_self = true;
+    if self.config.deterministic.is_some() {
+      self.db.add_solution_at(self.exchanges, self.id, sol);
+    } else {
+      self.db.add_solution(sol);
+    }
+  }
+  /// Stops watching clauses satisfied at level 0, which stay satisfied for every solver, and
+  /// removes the learnt ones from the database so they stop counting towards its budget.
+  fn remove_satisfied(&mut self) {
+    debug_assert_eq!(self.level, 0);
+    let satisfied = self.watch_list.remove_satisfied(&self.assignments);
+    if !satisfied.is_empty() {
+      self.db.forget_learnts(&satisfied);
+    }
+  }
+  /// Adds a clause written by another solver, skipping it if this solver already imported it.
+  /// The clause is watched in place, sharing its literals with every other solver using it.
+  fn import(&mut self, transfer: ClauseRef) -> Option<ClauseRef> {
+    use hashbrown::hash_map::Entry;
+    match self.imported.entry(Arc::as_ptr(&transfer.inner) as usize) {
+      Entry::Occupied(_) => None,
+      Entry::Vacant(ent) => {
+        ent.insert(Arc::downgrade(&transfer.inner));
+        self.add_transfer(transfer)
+      },
+    }
   }
 
   fn add_transfer(&mut self, transfer: ClauseRef) -> Option<ClauseRef> {
@@ -165,6 +661,10 @@ impl Solver {
         .add_transfer(&self.assignments, &self.causes, &self.levels, &transfer);
     if let Some(next_lit) = transfer_conf {
       if let Some(lvl) = self.levels[next_lit.var()] {
+        // falsified by the assumptions alone, which cannot be backtracked
+        if self.frontier > 0 && lvl <= self.frontier && self.level == self.frontier {
+          return Some(transfer);
+        }
         self.backtrack_to(lvl.saturating_sub(1));
         return self.add_transfer(transfer);
       }
@@ -173,36 +673,684 @@ impl Solver {
     None
   }
 
-  /// gets the final assignments for this solver
-  /// panics if any variable is still null.
-  pub fn final_assignments(&self) -> Vec<bool> {
-    self.assignments.iter().map(|&i| i.unwrap()).collect()
+  /// Assigns at level 0 every variable which appears with only one polarity across the initial
+  /// clauses not yet satisfied, repeating until no pure literals remain.
+  /// Must be called before solving. Returns the number of variables assigned.
+  /// These assignments only preserve satisfiability, not every model, so they are recorded
+  /// apart from units. Adding a clause or an assumption, counting models or resetting drops
+  /// them again, along with every learnt clause, as those may rely on them.
+  pub fn eliminate_pure_literals(&mut self) -> usize {
+    assert_eq!(self.level, 0);
+    let mut eliminated = 0;
+    loop {
+      // (appears positively, appears negatively) for each variable
+      let mut polarities = vec![(false, false); self.assignments.len()];
+      let assns = &self.assignments;
+      self
+        .db
+        .initial()
+        .iter()
+        .filter(|clause| {
+          !clause
+            .literals
+            .iter()
+            .any(|lit| lit.assn(assns) == Some(true))
+        })
+        .flat_map(|clause| clause.literals.iter())
+        .filter(|lit| lit.assn(assns).is_none())
+        .for_each(|lit| {
+          let seen = &mut polarities[lit.var()];
+          if lit.negated() {
+            seen.1 = true;
+          } else {
+            seen.0 = true;
+          }
+        });
+      let pure = polarities
+        .into_iter()
+        .enumerate()
+        .filter_map(|(var, seen)| match seen {
+          (true, false) => Some(Literal::new(var as u32, false)),
+          (false, true) => Some(Literal::new(var as u32, true)),
+          _ => None,
+        })
+        .collect::<Vec<_>>();
+      if pure.is_empty() {
+        return eliminated;
+      }
+      for lit in pure {
+        // the negation of a pure literal only occurs in satisfied clauses, so nothing can
+        // conflict here
+        let conflict = self.with(lit, None);
+        debug_assert_eq!(conflict, None);
+        self.pure_literals.push(lit);
+        eliminated += 1;
+      }
+    }
+  }
+  /// Removes initial clauses subsumed by other initial clauses, see `ClauseDatabase::subsume`.
+  /// Must be called before solving and before replicating, and returns none if the database
+  /// is already shared. Otherwise returns the number of clauses removed.
+  pub fn subsume(&mut self) -> Option<usize> {
+    assert_eq!(self.level, 0);
+    let removed = Arc::get_mut(&mut self.db)?.subsume();
+    self.stats.record(Record::Subsumed(removed));
+    if removed > 0 {
+      self.rewatch();
+    }
+    Some(removed)
+  }
+  /// Removes literals from initial clauses by self-subsuming resolution, see
+  /// `ClauseDatabase::strengthen`.
+  /// Must be called before solving and before replicating, and returns none if the database
+  /// is already shared. Otherwise returns the number of literals removed.
+  pub fn strengthen(&mut self) -> Option<usize> {
+    assert_eq!(self.level, 0);
+    let removed = Arc::get_mut(&mut self.db)?.strengthen();
+    if removed > 0 {
+      self.rewatch();
+    }
+    Some(removed)
+  }
+  /// Rewrites the initial clauses under the level 0 assignments, see
+  /// `ClauseDatabase::compact_initial`, which shrinks the clauses searched from then on.
+  /// Must be called at level 0 with nothing left to propagate, and returns none if the database
+  /// is shared. Otherwise returns the number of satisfied clauses dropped. As with `reset`,
+  /// learnt clauses which were not written to the database are forgotten. Pure literals
+  /// chosen so far become part of the compacted formula, which then only has the models
+  /// agreeing with them.
+  pub fn compact_initial(&mut self) -> Option<usize> {
+    assert_eq!(self.level, 0);
+    assert!(self.pending.is_none(), "Must propagate before compacting");
+    let removed = Arc::get_mut(&mut self.db)?.compact_initial(&self.assignments);
+    self.pure_literals.clear();
+    self.rewatch();
+    Some(removed)
+  }
+  /// Rebuilds the watch list from the database's clauses after they were modified, including
+  /// any learnt clauses kept across a reset, then replays the level 0 assignments against it.
+  fn rewatch(&mut self) {
+    assert_eq!(self.level, 0);
+    let (wl, units) = WatchList::watching(
+      &self.db,
+      self.db.iter(),
+      self.config.watch_selection,
+      &self.var_state,
+    );
+    self.watch_list = wl;
+    let trail = std::mem::take(&mut self.assignment_trail);
+    for lit in trail.iter() {
+      self.assignments[lit.var()] = None;
+      self.levels[lit.var()] = None;
+      self.causes[lit.var()] = None;
+      self.free_vars += 1;
+    }
+    for (cause, lit) in units {
+      if let Some(conflict) = self.with(lit, Some(cause)) {
+        self.prove(&[], &conflict);
+        self.db.add_solution(DbSolution::Unsat);
+        return;
+      }
+    }
+    for lit in trail {
+      if lit.assn(&self.assignments).is_some() {
+        continue;
+      }
+      if let Some(conflict) = self.with(lit, None) {
+        self.prove(&[], &conflict);
+        self.db.add_solution(DbSolution::Unsat);
+        return;
+      }
+    }
+  }
+  /// Unassigns the literals chosen by `eliminate_pure_literals` and every level 0 assignment
+  /// made after them, and forgets learnt clauses, which drop level 0 literals and so may only
+  /// hold given the pure literals. The watch list must be rebuilt afterwards.
+  /// Returns whether there were any pure literals to drop.
+  fn drop_pure_literals(&mut self) -> bool {
+    assert_eq!(self.level, 0);
+    let first = match self
+      .assignment_trail
+      .iter()
+      .position(|lit| self.pure_literals.contains(lit))
+    {
+      None => return false,
+      Some(first) => first,
+    };
+    self.pure_literals.clear();
+    for lit in self.assignment_trail.drain(first..) {
+      self.assignments[lit.var()] = None;
+      self.levels[lit.var()] = None;
+      self.causes[lit.var()] = None;
+      self.free_vars += 1;
+    }
+    let learnts = self
+      .watch_list
+      .learnt_clauses()
+      .into_iter()
+      .cloned()
+      .chain(self.db.learnt_iter())
+      .collect::<Vec<_>>();
+    self.db.forget_learnts(&learnts);
+    true
+  }
+  /// Assumes lit in every following solve until it is popped. Backtracks to level 0 and forgets
+  /// any model found so far, as it may not satisfy lit, including the one in the shared
+  /// database. Replicas made afterwards start with the same assumptions, but those made before
+  /// are not constrained by lit.
+  /// Pure literals eliminated so far are dropped, as lit may contradict them.
+  /// Panics if the variable of lit was eliminated.
+  pub fn push_assumption(&mut self, lit: Literal) {
+    assert!(
+      self.db.model_extension().vars().all(|var| var != lit.var()),
+      "assumed an eliminated variable"
+    );
+    self.assumptions.push(lit);
+    self.forget_model();
+    if self.drop_pure_literals() {
+      self.rewatch();
+    }
+  }
+  /// Stops assuming the most recently pushed literal and returns it, forgetting any model
+  /// found so far as there may now be others.
+  pub fn pop_assumption(&mut self) -> Option<Literal> {
+    let lit = self.assumptions.pop()?;
+    self.forget_model();
+    Some(lit)
+  }
+  /// Returns the literals which are currently assumed, in the order they were pushed
+  pub fn assumptions(&self) -> &[Literal] { &self.assumptions }
+  fn forget_model(&mut self) {
+    self.pending = None;
+    self.backtrack_to(0);
+    if matches!(self.db.get_solution(), DbSolution::Sat(_)) {
+      self.db.clear_solution();
+    }
+  }
+  /// Assigns each assumption at its own level, returning false if one conflicts with the
+  /// others or the formula.
+  fn assume(&mut self) -> bool {
+    for i in 0..self.assumptions.len() {
+      let lit = self.assumptions[i];
+      self.frontier = self.next_level();
+      match lit.assn(&self.assignments) {
+        Some(true) => continue,
+        Some(false) => {
+          self.failed = self.assumptions_implying(std::iter::once(lit.var()));
+          self.failed.push(lit);
+        },
+        None => match self.with(lit, None) {
+          None => continue,
+          Some(conflict) => {
+            self.failed = self.assumptions_implying(conflict.literals.iter().map(|lit| lit.var()));
+          },
+        },
+      }
+      self.failed.sort_unstable();
+      self.failed.dedup();
+      return false;
+    }
+    true
+  }
+  /// Returns the assumptions which the assignments of vars were implied by, found by following
+  /// their reasons back to the decisions. Must only be called when every decision is an
+  /// assumption.
+  fn assumptions_implying(&self, vars: impl Iterator<Item = usize>) -> Vec<Literal> {
+    let mut seen = HashSet::default();
+    let mut stack = vars.collect::<Vec<_>>();
+    let mut assumed = vec![];
+    while let Some(var) = stack.pop() {
+      if !seen.insert(var) {
+        continue;
+      }
+      match (self.levels[var], &self.causes[var]) {
+        (None, _) | (Some(0), _) => (),
+        (Some(_), Some(reason)) => stack.extend(reason.literals.iter().map(|lit| lit.var())),
+        (Some(_), None) => assumed.push(Literal::new(var as u32, !self.assignments[var].unwrap())),
+      }
+    }
+    assumed.sort_unstable();
+    assumed
+  }
+  /// Returns the assumptions which the last solve returning UNSAT showed cannot all hold
+  /// together, or nothing if it did not return UNSAT because of assumptions. An empty set of
+  /// failed assumptions after UNSAT means the formula is UNSAT by itself.
+  pub fn failed_assumptions(&self) -> &[Literal] { &self.failed }
+  /// Adds a clause to the formula, so that solving again only finds solutions which also
+  /// satisfy it. Backtracks to level 0 and forgets any solution found so far, but keeps learnt
+  /// clauses, which remain implied by the larger formula, unless pure literals were eliminated.
+  /// The clause must not contain eliminated variables, and is not covered by LRAT proofs.
+  /// Returns false without adding it if the database is shared with replicas.
+  pub fn add_clause(&mut self, literals: Vec<Literal>) -> bool {
+    self.pending = None;
+    self.backtrack_to(0);
+    let db = match Arc::get_mut(&mut self.db) {
+      None => return false,
+      Some(db) => db,
+    };
+    let eliminated = db.model_extension().vars().collect::<HashSet<_>>();
+    assert!(
+      literals.iter().all(|lit| !eliminated.contains(&lit.var())),
+      "added a clause with an eliminated variable"
+    );
+    let clause = Clause::from(literals);
+    db.clear_solution();
+    if clause.is_empty() {
+      db.add_solution(DbSolution::Unsat);
+      return true;
+    }
+    db.add_initial(clause);
+    // pure literals of the old formula may not be pure in the new one
+    self.drop_pure_literals();
+    self.rewatch();
+    true
+  }
+  /// Finds every model of the formula, or at most limit of them, by adding a clause blocking
+  /// each model found before solving again. Each model differs from the others in at least one
+  /// variable which was not eliminated. The blocking clauses remain part of the formula.
+  /// Panics if the database is shared with replicas.
+  pub fn solve_all(&mut self, limit: Option<usize>) -> Vec<Vec<bool>> {
+    let limit = limit.unwrap_or(usize::MAX);
+    self.solutions().take(limit).collect()
+  }
+  /// Lazily finds the models of the formula one at a time, as `solve_all` does, adding the
+  /// clause blocking each model before it is returned.
+  /// Panics if the database is shared with replicas.
+  pub fn solutions(&mut self) -> impl Iterator<Item = Vec<bool>> + '_ {
+    let eliminated = self.db.model_extension().vars().collect::<HashSet<_>>();
+    let vars = (0..self.assignments.len())
+      .filter(|var| !eliminated.contains(var))
+      .collect::<Vec<_>>();
+    self.models(vars)
+  }
+  /// Finds every distinct assignment of vars which extends to a model of the formula, or at
+  /// most limit of them. Each is returned as the value of every variable in vars, and none for
+  /// the others.
+  /// Only the projected literals are blocked after each model is found, which is what makes
+  /// the enumeration complete over the projection while never repeating an assignment of it.
+  /// Blocking every literal instead would enumerate each extension separately. The blocking
+  /// clauses remain part of the formula.
+  /// Panics if the database is shared with replicas or any of vars was eliminated.
+  pub fn solve_projected(
+    &mut self,
+    vars: &[usize],
+    limit: Option<usize>,
+  ) -> Vec<Vec<Option<bool>>> {
+    self
+      .models(vars.to_vec())
+      .take(limit.unwrap_or(usize::MAX))
+      .map(|model| {
+        let mut projected = vec![None; model.len()];
+        vars
+          .iter()
+          .for_each(|&var| projected[var] = Some(model[var]));
+        projected
+      })
+      .collect()
+  }
+  /// Solves each time the next model is asked for, blocking the assignment of vars in each
+  /// model found, until the formula is UNSAT.
+  fn models(&mut self, vars: Vec<usize>) -> impl Iterator<Item = Vec<bool>> + '_ {
+    std::iter::from_fn(move || {
+      let model = match self.solve() {
+        SolveResult::Sat(model) => model,
+        SolveResult::Unsat | SolveResult::Unknown => return None,
+      };
+      let blocking = vars
+        .iter()
+        .map(|&var| Literal::new(var as u32, model[var]))
+        .collect();
+      assert!(
+        self.add_clause(blocking),
+        "cannot enumerate models of a shared database"
+      );
+      Some(model)
+    })
+    .fuse()
+  }
+  /// Counts the models of the formula exactly by exhaustive DPLL search, without caching
+  /// components, so it takes exponential time and is only meant for small formulas, such as
+  /// when checking enumeration.
+  /// Returns none if more than max_nodes decisions would be needed, or the count does not fit.
+  /// Eliminated variables are not counted, as each model of the remaining formula extends to
+  /// at least one model of the original. Backtracks to level 0, and drops any eliminated pure
+  /// literals, as they would exclude models.
+  pub fn count_models(&mut self, max_nodes: Option<u64>) -> Option<u128> {
+    self.pending = None;
+    self.backtrack_to(0);
+    if self.drop_pure_literals() {
+      self.rewatch();
+    }
+    if self.db.get_solution() == DbSolution::Unsat {
+      return Some(0);
+    }
+    let mut nodes = 0;
+    self.count_below(&mut nodes, max_nodes)
+  }
+  /// Counts the models extending the current assignments, which must not conflict.
+  fn count_below(&mut self, nodes: &mut u64, max_nodes: Option<u64>) -> Option<u128> {
+    let assignments = &self.assignments;
+    let satisfied = self.db.initial().iter().all(|clause| {
+      clause
+        .literals
+        .iter()
+        .any(|lit| lit.assn(assignments) == Some(true))
+    });
+    // every free variable may take either value once every clause is satisfied
+    if satisfied {
+      return 1u128.checked_shl(self.num_free() as u32);
+    }
+    let var = self
+      .first_unassigned()
+      .expect("an unsatisfied clause has no unassigned variable");
+    let mut count = 0u128;
+    for val in [false, true] {
+      *nodes += 1;
+      if max_nodes.is_some_and(|max| *nodes > max) {
+        self.backtrack_to(0);
+        return None;
+      }
+      let level = self.next_level();
+      if self.with(Literal::new(var as u32, !val), None).is_none() {
+        count = count.checked_add(self.count_below(nodes, max_nodes)?)?;
+      }
+      self.backtrack_to(level - 1);
+    }
+    Some(count)
+  }
+  /// Resets this solver so that it can search again from scratch, for example after more
+  /// clauses were added to its database.
+  ///
+  /// Cleared: every assignment along with its level and cause, saved phases, variable
+  /// activities, the restart schedule and random number generator, statistics, and the solution
+  /// stored in the database, which is shared with any replicas.
+  ///
+  /// Preserved: the clause database including learnt clauses and eliminated variables, the
+  /// config, and the progress hook. Units from the database are propagated again at level 0.
+  pub fn reset(&mut self) {
+    self.pending = None;
+    self.backtrack_to(0);
+    self.drop_pure_literals();
+    for lit in self.assignment_trail.drain(..) {
+      self.assignments[lit.var()] = None;
+      self.levels[lit.var()] = None;
+      self.causes[lit.var()] = None;
+      self.free_vars += 1;
+    }
+    self.db.clear_solution();
+    self.imported.clear();
+    let decay_rate = self.var_state.decay_rate;
+    self.var_state = VariableState::from(&*self.db);
+    self.var_state.decay_rate = decay_rate;
+    for var in self.db.model_extension().vars() {
+      self.var_state.remove(var);
+    }
+    let config = self.config;
+    self.configure(&config);
+    self.stats.reset();
+    self.rewatch();
+  }
+  /// Returns the activity of each variable used to pick decisions, which is 0 for eliminated
+  /// variables. Passing these to `import_activities` after `reset` continues the search from
+  /// the same variables instead of starting over from the initial clauses.
+  pub fn export_activities(&self) -> Vec<f32> {
+    (0..self.num_vars())
+      .map(|var| self.var_state.activity(var))
+      .collect()
+  }
+  /// Sets the activity of each variable from a previous call to `export_activities`, clamping
+  /// them to be finite and non-negative. Eliminated variables stay eliminated.
+  /// Panics if the number of activities differs from the number of variables.
+  pub fn import_activities(&mut self, activities: &[f32]) {
+    assert_eq!(
+      activities.len(),
+      self.num_vars(),
+      "expected an activity for each variable"
+    );
+    for (var, &activity) in activities.iter().enumerate() {
+      self.var_state.set_activity(var, activity);
+    }
+  }
+  /// Resets this solver's statistics without touching anything else, so that they only cover
+  /// the solves which follow, such as a query after adding clauses. Limits counted by the
+  /// statistics, such as the most restarts, also start over.
+  pub fn reset_stats(&mut self) { self.stats.reset(); }
+  /// Returns a copy of this solver's statistics so far, which does not depend on the layout
+  /// of `Stats`.
+  pub fn statistics(&self) -> StatsSnapshot { self.stats.snapshot() }
+  /// Eliminates variables by resolving away all clauses they occur in, whenever that does not
+  /// add more than grow clauses, see `ClauseDatabase::eliminate`.
+  /// Must be called before solving and before replicating, and returns none if the database
+  /// is already shared. Otherwise returns the number of variables eliminated.
+  pub fn bounded_variable_elimination(&mut self, grow: usize) -> Option<usize> {
+    assert_eq!(self.level, 0);
+    let db = Arc::get_mut(&mut self.db)?;
+    let assignments = &self.assignments;
+    let var_state = &mut self.var_state;
+    let eliminated = (0..db.max_var)
+      .filter(|&var| assignments[var].is_none() && db.eliminate(var, grow))
+      .inspect(|&var| var_state.remove(var))
+      .count();
+    self.free_vars -= eliminated;
+    if eliminated > 0 {
+      self.rewatch();
+    }
+    Some(eliminated)
+  }
+  /// gets the final assignments for this solver, including eliminated variables.
+  /// panics if any other variable is still null.
+  pub fn final_assignments(&self) -> Vec<bool> { self.reconstruct_model(&self.assignments) }
+  /// Completes a model of the formula left after preprocessing into a model of the original
+  /// formula, by assigning eliminated variables in reverse order of elimination.
+  /// panics if any variable which was not eliminated is unassigned in partial.
+  pub fn reconstruct_model(&self, partial: &[Option<bool>]) -> Vec<bool> {
+    let mut assns = partial.to_vec();
+    self.db.model_extension().extend(&mut assns);
+    assns.into_iter().map(Option::unwrap).collect()
+  }
+  /// Returns true if model is a satisfying assignment for this solver's formula.
+  pub fn verify(&self, model: &[bool]) -> bool { self.db.verify(model) }
+  /// Greedily unassigns variables of model, which must satisfy the formula, as long as every
+  /// initial clause, including those removed by variable elimination, keeps a true literal.
+  /// Variables are tried in order, and the ones which remain assigned are needed by the others,
+  /// so the result is subset-minimal but not necessarily the smallest possible.
+  /// Panics if model does not satisfy the formula.
+  pub fn minimize_model(&self, model: &[bool]) -> Vec<Option<bool>> {
+    assert!(
+      self.verify(model),
+      "can only minimize a model of the formula"
+    );
+    let clauses = self
+      .db
+      .initial()
+      .iter()
+      .chain(self.db.model_extension().clauses())
+      .collect::<Vec<_>>();
+    // number of true literals in each clause, and the clauses each literal is in
+    let mut num_true = vec![0; clauses.len()];
+    let mut occurrences = vec![vec![]; 2 * model.len()];
+    for (i, clause) in clauses.iter().enumerate() {
+      for lit in clause.literals.iter() {
+        occurrences[lit.raw() as usize].push(i);
+        if model[lit.var()] ^ lit.negated() {
+          num_true[i] += 1;
+        }
+      }
+    }
+    let mut minimal = model.iter().map(|&val| Some(val)).collect::<Vec<_>>();
+    for (var, &val) in model.iter().enumerate() {
+      let true_lit = Literal::new(var as u32, !val);
+      let containing = &occurrences[true_lit.raw() as usize];
+      if containing.iter().all(|&i| num_true[i] > 1) {
+        containing.iter().for_each(|&i| num_true[i] -= 1);
+        minimal[var] = None;
+      }
+    }
+    minimal
+  }
+  /// Searches for a model of the initial clauses by WalkSAT local search, which is incomplete
+  /// but can be much faster than CDCL on some satisfiable formulas. Each of max_tries starts
+  /// from an assignment picked by this solver's seeded random number generator, and makes up
+  /// to max_flips flips. Each flip picks a falsified clause and flips one of its variables,
+  /// preferring one which falsifies no other clause, or else a random one with probability
+  /// noise, or else the one falsifying the fewest other clauses.
+  /// Returns none if no model was found, which does not mean that there is none. This does
+  /// not change the state of the search.
+  /// Panics if noise is not a probability between 0 and 1.
+  pub fn walksat(&mut self, max_flips: usize, max_tries: usize, noise: f64) -> Option<Vec<bool>> {
+    assert!(
+      (0.0..=1.0).contains(&noise),
+      "noise must be a probability between 0 and 1"
+    );
+    // holding the database lets the clauses be borrowed while the generator is used
+    let db = Arc::clone(&self.db);
+    let clauses = db.initial();
+    let mut occurrences = vec![vec![]; 2 * self.num_vars()];
+    for (i, clause) in clauses.iter().enumerate() {
+      for lit in clause.literals.iter() {
+        occurrences[lit.raw() as usize].push(i);
+      }
+    }
+    let eliminated = self.db.model_extension().vars().collect::<HashSet<_>>();
+    for _ in 0..max_tries {
+      let mut assns = (0..self.num_vars())
+        .map(|var| (!eliminated.contains(&var)).then(|| self.rng.gen::<bool>()))
+        .collect::<Vec<_>>();
+      let mut num_true = clauses
+        .iter()
+        .map(|clause| {
+          let true_lits = clause.literals.iter();
+          true_lits
+            .filter(|lit| lit.assn(&assns) == Some(true))
+            .count()
+        })
+        .collect::<Vec<_>>();
+      // falsified clauses, along with the position of each clause in it
+      let mut falsified = (0..clauses.len())
+        .filter(|&i| num_true[i] == 0)
+        .collect::<Vec<_>>();
+      let mut position = vec![usize::MAX; clauses.len()];
+      for (pos, &i) in falsified.iter().enumerate() {
+        position[i] = pos;
+      }
+      debug_assert_eq!(self.db.clause_status_counts(&assns).1, falsified.len());
+      for _ in 0..max_flips {
+        if falsified.is_empty() {
+          break;
+        }
+        let clause = &clauses[falsified[self.rng.gen_range(0, falsified.len())]];
+        // number of clauses which flipping the variable of each literal would falsify
+        let breaks = clause.literals.iter().map(|lit| {
+          occurrences[(!lit).raw() as usize]
+            .iter()
+            .filter(|&&i| num_true[i] == 1)
+            .count()
+        });
+        let (min_lit, min_breaks) = clause
+          .literals
+          .iter()
+          .zip(breaks)
+          .min_by_key(|&(_, breaks)| breaks)?;
+        let lit = if min_breaks > 0 && self.rng.gen_bool(noise) {
+          clause.literals[self.rng.gen_range(0, clause.literals.len())]
+        } else {
+          *min_lit
+        };
+        assns[lit.var()] = Some(lit.val());
+        for &i in occurrences[lit.raw() as usize].iter() {
+          num_true[i] += 1;
+          if num_true[i] == 1 {
+            let pos = position[i];
+            falsified.swap_remove(pos);
+            if let Some(&moved) = falsified.get(pos) {
+              position[moved] = pos;
+            }
+          }
+        }
+        for &i in occurrences[(!lit).raw() as usize].iter() {
+          num_true[i] -= 1;
+          if num_true[i] == 0 {
+            position[i] = falsified.len();
+            falsified.push(i);
+          }
+        }
+      }
+      if falsified.is_empty() {
+        for &var in eliminated.iter() {
+          assns[var] = None;
+        }
+        return Some(self.reconstruct_model(&assns));
+      }
+    }
+    None
   }
   /// returns whether there are still unassigned variables for
   /// this solver.
-  pub fn has_unassigned_vars(&self) -> bool { self.assignment_trail.len() < self.assignments.len() }
-  /// returns the reason for a var's assignment if it exists
-  pub fn reason(&self, var: usize) -> Option<&ClauseRef> { self.causes[var].as_ref() }
-  /// Analyzes a conflict for a given variable
-  fn analyze(&mut self, src_clause: &ClauseRef, decision_level: usize) -> (Clause, usize) {
+  pub fn has_unassigned_vars(&self) -> bool { self.num_free() > 0 }
+  /// returns the number of variables which are neither assigned nor eliminated.
+  pub fn num_free(&self) -> usize {
+    debug_assert_eq!(
+      self.free_vars + self.assignment_trail.len() + self.db.num_eliminated(),
+      self.assignments.len()
+    );
+    self.free_vars
+  }
+  /// returns the number of variables, which every variable passed to this solver is below.
+  pub fn num_vars(&self) -> usize { self.assignments.len() }
+  /// returns the reason for a var's assignment if it exists, or none if var is out of range
+  pub fn reason(&self, var: usize) -> Option<&ClauseRef> { self.causes.get(var)?.as_ref() }
+  /// returns the value assigned to var, or none if it is unassigned or out of range
+  pub fn value(&self, var: usize) -> Option<bool> { *self.assignments.get(var)? }
+  /// returns the decision level var was assigned at, or none if it is unassigned or out of
+  /// range
+  pub fn level_of(&self, var: usize) -> Option<usize> { *self.levels.get(var)? }
+  /// returns the reason for a var's assignment, where var must be in range
+  #[inline]
+  fn cause(&self, var: usize) -> Option<&ClauseRef> {
+    debug_assert!(var < self.num_vars(), "variable {} out of range", var);
+    self.causes[var].as_ref()
+  }
+  /// Analyzes a conflict, returning the learnt clause along with the level to backtrack to.
+  /// Returns none if the conflict resolves to the empty clause, which happens exactly when
+  /// every literal of the conflict was assigned at level 0.
+  fn analyze(&mut self, src_clause: &ClauseRef) -> Option<(Clause, usize)> {
+    let analysis = self.analyze_detailed(src_clause)?;
+    Some((analysis.learnt, analysis.backtrack_level))
+  }
+  /// Analyzes a conflict found at the current level like `analyze_and_backtrack`, but without
+  /// learning the clause or backtracking, and also returns the clauses resolved to derive it.
+  /// Bumps the activity of the variables and clauses involved. Returns none if every literal of
+  /// the conflict was assigned at level 0.
+  pub fn analyze_detailed(&mut self, src_clause: &ClauseRef) -> Option<ConflictAnalysis> {
     use hashbrown::hash_map::Entry;
+    let decision_level = self.level;
+    if src_clause
+      .literals
+      .iter()
+      .all(|lit| self.levels[lit.var()] == Some(0))
+    {
+      return None;
+    }
     let mut learnt: Vec<Literal> = vec![];
     // TODO convert seen, removable, to reused vectors?
-    // let mut seen: HashSet<usize> = HashSet::new();
+    // let mut seen: HashSet<usize> = HashSet::default();
     let mut seen = self.analyze_seen.borrow_mut();
     let curr_len = self.assignment_trail.len() - 1;
     let var_state = &mut self.var_state;
     let levels = &self.levels;
     let trail = &self.assignment_trail;
     let causes = &self.causes;
+    let clause_bump = self.config.clause_bump;
+    let mut antecedents = vec![];
     let mut learn_until_uip =
       |cref: &ClauseRef, remaining: usize, trail_idx: usize, previous_lit: Option<Literal>| {
-        cref.boost();
+        antecedents.push(cref.clone());
+        cref.boost(clause_bump);
         let count: usize = cref
           .literals
           .iter()
           // only find new literals
-          .filter(|&&lit| previous_lit.map_or(true, |prev| prev != lit))
+          .filter(|&&lit| previous_lit != Some(lit))
           .filter(|&lit| match &levels[lit.var()] {
             None | Some(0) => false,
             Some(lvl) => match seen.entry(lit.var()) {
@@ -234,17 +1382,60 @@ impl Solver {
     let mut causes = learn_until_uip(src_clause, 0, curr_len, None);
     while causes.1 > 0 {
       let conflict = causes.0.expect("No cause found in analyze?");
-      causes = learn_until_uip(&conflict, causes.1, causes.2, Some(causes.3));
+      causes = learn_until_uip(conflict, causes.1, causes.2, Some(causes.3));
     }
     // minimization before adding asserting literal
-    learnt.retain(|lit| self.reason(lit.var()).is_none() || !self.lit_redundant(*lit, &mut seen));
+    learnt.retain(|lit| self.cause(lit.var()).is_none() || !self.lit_redundant(*lit, &mut seen));
 
     // add asserting literal
     learnt.push(!causes.3);
     seen.clear();
+    // each variable is only seen once, so sorting is enough
+    learnt.sort_unstable();
+    let backtrack_level = self.backtrack_level(&learnt);
+    Some(ConflictAnalysis {
+      learnt: Clause::from_sorted_unchecked(learnt),
+      backtrack_level,
+      resolutions: antecedents.len() - 1,
+      antecedents,
+    })
+  }
+  /// Returns the implication graph of the current trail, which is what conflict analysis
+  /// traverses, ending at the conflict. Each implied literal has an edge from the negation of
+  /// every other literal of its reason, all of which were assigned before it.
+  pub fn implication_graph(&self, conflict: &ClauseRef) -> ImplGraph {
+    let nodes = self
+      .assignment_trail
+      .iter()
+      .map(|&lit| ImplNode {
+        lit,
+        level: self.levels[lit.var()].unwrap(),
+        cause: self.causes[lit.var()].clone(),
+      })
+      .collect::<Vec<_>>();
+    let edges = nodes
+      .iter()
+      .flat_map(|node| {
+        let antecedents = node.cause.iter().flat_map(|cause| cause.literals.iter());
+        antecedents
+          .filter(move |lit| lit.var() != node.lit.var())
+          .map(move |lit| (!*lit, node.lit))
+      })
+      .collect();
+    let conflict = conflict.literals.iter().map(|&lit| !lit).collect();
+    ImplGraph {
+      nodes,
+      edges,
+      conflict,
+    }
+  }
+  /// Returns the second highest level among the literals of a learnt clause, where it becomes
+  /// unit. If every literal is at the same level, that level is returned, as there is no level
+  /// where the clause becomes unit.
+  fn backtrack_level(&self, learnt: &[Literal]) -> usize {
     if learnt.len() == 1 {
       // backtrack to 0
-      return (Clause::from(learnt), 0);
+      return 0;
     }
     let mut levels = learnt
       .iter()
@@ -252,7 +1443,7 @@ impl Solver {
     let curr_max = levels.next().unwrap();
     let mut others = levels.filter(|&lvl| lvl != curr_max);
     let (max, second) = match others.next() {
-      None => return (Clause::from(learnt), curr_max),
+      None => return curr_max,
       Some(lvl) if lvl > curr_max => (lvl, curr_max),
       Some(lvl) => (curr_max, lvl),
     };
@@ -263,15 +1454,18 @@ impl Solver {
       Ordering::Equal => (max, second),
       Ordering::Less => (max, second.max(next)),
     });
-    (Clause::from(learnt), second)
+    second
   }
   pub fn next_level(&mut self) -> usize {
     self.level_indeces.push(self.assignment_trail.len());
     self.level += 1;
+    self.stats.record(Record::Level(self.level));
     self.level
   }
   /// revert to given level, retaining all state at that level.
+  /// Never goes below the assumptions while solving.
   fn backtrack_to(&mut self, lvl: usize) {
+    let lvl = lvl.max(self.frontier);
     if lvl >= self.level {
       return;
     }
@@ -282,21 +1476,80 @@ impl Solver {
       let var = lit.var();
       assert_ne!(self.assignments[var].take(), None);
       assert_ne!(self.levels[var].take(), None);
-      self.polarities[var] = lit.val();
+      self.free_vars += 1;
+      if self.config.phase_saving {
+        self.polarities[var] = lit.val();
+      }
       self.causes[var].take();
-      self.var_state.enable(var);
+      self.var_state.enable_lazily(var);
     }
     assert_eq!(self.level_indeces.len(), lvl);
   }
   pub fn from_dimacs<S: AsRef<std::path::Path>>(s: S) -> std::io::Result<Self> {
+    SolverBuilder::new().build_from_dimacs(s)
+  }
+  /// Creates a solver for the clauses given as DIMACS literals, where variables are numbered
+  /// from 1 up to max_var and negative numbers are negated literals.
+  /// Panics if a literal is 0 or its variable is larger than max_var.
+  pub fn from_clauses(clauses: Vec<Vec<i32>>, max_var: usize) -> Self {
+    SolverBuilder::new().build_from_clauses(clauses, max_var)
+  }
+  /// Creates a solver which shares an existing database with any other solvers created from
+  /// it, so that many solvers can be created without parsing the formula again.
+  /// Each solver takes the next free solver slot of the database, and they share both learnt
+  /// clauses and solutions, so they should only differ in their assumptions and search
+  /// parameters. Panics if every slot is taken, see `ClauseDatabase::reserve_solvers`.
+  pub fn with_database(db: Arc<ClauseDatabase>) -> Self {
+    SolverBuilder::new().build_with_database(db)
+  }
+  /// Creates a solver for the formula in the given DIMACS file with the given config.
+  pub(crate) fn from_dimacs_with<S: AsRef<std::path::Path>>(
+    s: S,
+    config: &SolverConfig,
+  ) -> std::io::Result<Self> {
     use crate::dimacs::from_dimacs;
     let (clauses, max_var) = from_dimacs(s)?;
-    let db = ClauseDatabase::new(max_var, clauses);
-    let (wl, units) = WatchList::new(&db);
-    let var_state = VariableState::from(&db);
+    Ok(Self::new(max_var, clauses, config))
+  }
+  /// Creates a solver for the clauses given as DIMACS literals with the given config.
+  pub(crate) fn from_clauses_with(
+    clauses: Vec<Vec<i32>>,
+    max_var: usize,
+    config: &SolverConfig,
+  ) -> Self {
+    let clauses = clauses
+      .into_iter()
+      .map(|lits| {
+        assert!(!lits.contains(&0), "0 is not a DIMACS literal");
+        let mut clause = Clause::from(lits.into_iter().map(Literal::from).collect::<Vec<_>>());
+        clause.initial = true;
+        clause
+      })
+      .collect();
+    Self::new(max_var, clauses, config)
+  }
+  /// Creates a solver for the given initial clauses, propagating any units among them.
+  fn new(max_var: usize, clauses: Vec<Clause>, config: &SolverConfig) -> Self {
+    Self::from_database_with(Arc::new(ClauseDatabase::new(max_var, clauses)), config)
+  }
+  /// Creates a solver for the initial clauses of db with the given config, propagating any
+  /// units among them.
+  pub(crate) fn from_database_with(db: Arc<ClauseDatabase>, config: &SolverConfig) -> Self {
+    let id = db.next_id();
+    assert!(
+      id < db.num_solvers(),
+      "every solver slot of the database is taken"
+    );
+    let max_var = db.max_var;
+    let mut var_state = VariableState::from(&*db);
+    db.model_extension()
+      .vars()
+      .for_each(|var| var_state.remove(var));
+    let (wl, units) = WatchList::new(&db, config.watch_selection, &var_state);
     let mut solver = Self {
-      id: db.next_id(),
+      id,
       assignments: vec![None; max_var],
+      free_vars: max_var - db.num_eliminated(),
       causes: vec![None; max_var],
       assignment_trail: vec![],
       level_indeces: vec![],
@@ -305,17 +1558,42 @@ impl Solver {
       polarities: vec![false; max_var],
       var_state,
       latest_clauses: vec![0; db.num_solvers()],
-      db: Arc::new(db),
+      imported: HashMap::default(),
+      to_write: vec![],
+      exchanges: 0,
+      assumptions: vec![],
+      frontier: 0,
+      failed: vec![],
+      pure_literals: vec![],
+      next_id: db.next_clause_id() + id as u64,
+      pending: None,
+      db,
       level: 0,
-      restart_state: RestartState::new(RESTART_BASE, RESTART_INC),
+      restart_state: RestartState::new(config.restart_base, config.restart_inc),
+      config: *config,
+      rng: StdRng::seed_from_u64(config.seed),
+      progress: None,
+      lrat: None,
+      derivations: None,
+      found_by_self: false,
       stats: Stats::new(),
-      analyze_stack: RefCell::new(vec![]),
-      analyze_seen: RefCell::new(HashMap::new()),
+      analyze_seen: RefCell::new(HashMap::default()),
     };
+    solver.configure(config);
     for (cause, lit) in units {
-      assert_eq!(solver.with(lit, Some(cause.clone())), None, "UNSAT");
+      // A conflict between initial units means the formula is UNSAT by propagation alone
+      if solver.with(lit, Some(cause)).is_some() {
+        solver.db.add_solution(DbSolution::Unsat);
+        break;
+      }
     }
-    Ok(solver)
+    // propagating the units assigned every variable without conflict, so no clause is false
+    if !solver.has_unassigned_vars() && solver.db.get_solution() == DbSolution::Undecided {
+      solver
+        .db
+        .add_solution(DbSolution::Sat(solver.final_assignments()));
+    }
+    solver
   }
   /// Records a literal written at the current level, with a possible cause
   fn with(&mut self, lit: Literal, cause: Option<ClauseRef>) -> Option<ClauseRef> {
@@ -323,10 +1601,13 @@ impl Solver {
       // In the case there was no previous cause, we need to do one iteration
       None => {
         let mut units = vec![];
+        // decisions are checked once, since they may come from outside of propagation
         assert!(lit.assn(&self.assignments).is_none());
+        debug_assert_eq!(self.levels[lit.var()], None);
         self.assignment_trail.push(lit);
-        assert_eq!(self.levels[lit.var()].replace(self.level), None);
-        assert_eq!(self.assignments[lit.var()].replace(lit.val()), None);
+        self.levels[lit.var()] = Some(self.level);
+        self.assignments[lit.var()] = Some(lit.val());
+        self.free_vars -= 1;
         self.watch_list.set(lit, &self.assignments, &mut units);
         units
       },
@@ -340,24 +1621,42 @@ impl Solver {
       }
       self.assignment_trail.push(lit);
       self.stats.record(Record::Propogation);
-      assert_eq!(self.causes[lit.var()].replace(cause), None);
-      assert_eq!(self.levels[lit.var()].replace(self.level), None);
-      assert_eq!(self.assignments[lit.var()].replace(lit.val()), None);
+      // lit was just checked to be unassigned, and backtracking clears all three together
+      let prev_cause = self.causes[lit.var()].replace(cause);
+      debug_assert_eq!(prev_cause, None);
+      let prev_level = self.levels[lit.var()].replace(self.level);
+      debug_assert_eq!(prev_level, None);
+      self.assignments[lit.var()] = Some(lit.val());
+      self.free_vars -= 1;
       self.watch_list.set(lit, &self.assignments, &mut units)
     }
     None
   }
-  /// Chooese the next decision literal.
+  /// Chooese the next decision literal, or none if every variable is assigned or eliminated.
   /// Must take a mutable reference because it must modify the heap of assignments
-  fn choose_lit(&mut self) -> Literal {
-    assert!(self.has_unassigned_vars());
+  fn choose_lit(&mut self) -> Option<Literal> {
+    self.var_state.enable_unassigned(&self.assignments);
     let var = loop {
-      let next = self.var_state.take_highest_prio();
-      if self.assignments[next].is_none() {
-        break next;
+      match self.var_state.take_highest_prio() {
+        Some(next) if self.assignments[next].is_none() => break next,
+        Some(_) => continue,
+        // the queue may have lost track of some unassigned variable, so check by scanning
+        None => break self.first_unassigned()?,
       }
     };
-    Literal::new(var as u32, !self.polarities[var])
+    if !self.config.phase_saving && self.config.phase == Phase::Random {
+      return Some(self.random_polarity(var));
+    }
+    Some(Literal::new(var as u32, !self.polarities[var]))
+  }
+  /// Returns a literal of var with a polarity picked by this solver's seeded random number
+  /// generator, so that it is the same on every run with the same seed.
+  fn random_polarity(&mut self, var: usize) -> Literal { Literal::new(var as u32, self.rng.gen()) }
+  /// Returns the first variable which is neither assigned nor eliminated
+  fn first_unassigned(&self) -> Option<usize> {
+    let eliminated = self.db.model_extension().vars().collect::<HashSet<_>>();
+    (0..self.assignments.len())
+      .find(|var| self.assignments[*var].is_none() && !eliminated.contains(var))
   }
 
   /// Clones this solver and increments its id.
@@ -369,39 +1668,377 @@ impl Solver {
     out
   }
   pub fn id(&self) -> usize { self.id }
-  /// Replicates this one solver into multiple with the same state.
-  /// Returns none if replicate was called before.
-  pub fn replicate(mut self, n: usize) -> Option<Vec<Self>> {
-    let db = Arc::get_mut(&mut self.db)?;
-    self.latest_clauses = vec![0; n];
-    db.resize_to(n);
-    let mut replicas = (0..n - 1).map(|_| self.duplicate()).collect::<Vec<_>>();
+  /// returns the number of restarts this solver has performed.
+  pub fn num_restarts(&self) -> u32 { self.stats.restarts }
+  /// returns the current decision level, where 0 holds assignments made without any decision.
+  pub fn decision_level(&self) -> usize { self.level }
+  /// returns every assigned literal in the order they were assigned.
+  pub fn trail(&self) -> &[Literal] { &self.assignment_trail }
+  /// returns the number of clauses in this solver's watch list currently watching lit.
+  pub fn watch_count(&self, lit: Literal) -> usize { self.watch_list.watch_count(lit) }
+  /// returns the number of clauses in this solver's watch list currently watching each
+  /// variable, counting both of its literals, which shows where the formula is densest.
+  ///
+  /// ```
+  /// use small_sat::solver::Solver;
+  /// let solver = Solver::from_clauses(vec![vec![1, 2], vec![1, 3], vec![-2, -3, 1]], 3);
+  /// let histogram = solver.watch_histogram();
+  /// // rank variables by how many clauses watch them, most watched first
+  /// let mut vars = (0..histogram.len()).collect::<Vec<_>>();
+  /// vars.sort_by_key(|&var| std::cmp::Reverse(histogram[var]));
+  /// assert_eq!(vars[0], 0);
+  /// assert_eq!(histogram.iter().sum::<usize>(), 6);
+  /// ```
+  pub fn watch_histogram(&self) -> Vec<usize> { self.watch_list.occurrence_histogram() }
+  /// returns the literals assigned at the given decision level, in the order they were
+  /// assigned, starting with the decision itself. Empty for levels above the current one.
+  pub fn trail_at_level(&self, lvl: usize) -> &[Literal] {
+    if lvl > self.level {
+      return &[];
+    }
+    let start = lvl
+      .checked_sub(1)
+      .map_or(0, |prev| self.level_indeces[prev]);
+    let end = self
+      .level_indeces
+      .get(lvl)
+      .copied()
+      .unwrap_or(self.assignment_trail.len());
+    &self.assignment_trail[start..end]
+  }
+  /// Checks that the trail, assignments, and watch list of this solver agree with each other,
+  /// returning a description of the first inconsistency found. This walks every variable and
+  /// watch, so it is meant for debugging and tests, and costs nothing unless called.
+  pub fn check_invariants(&self) -> Result<(), String> {
+    if self.level_indeces.len() != self.level {
+      return Err(format!(
+        "{} levels started but at level {}",
+        self.level_indeces.len(),
+        self.level
+      ));
+    }
+    for var in 0..self.num_vars() {
+      let (assn, lvl) = (self.assignments[var], self.levels[var]);
+      if assn.is_some() != lvl.is_some() {
+        return Err(format!(
+          "variable {} is assigned {:?} at level {:?}",
+          var, assn, lvl
+        ));
+      }
+      if assn.is_none() && self.causes[var].is_some() {
+        return Err(format!("unassigned variable {} has a reason", var));
+      }
+    }
+    let mut on_trail = HashSet::default();
+    for lvl in 0..=self.level {
+      for &lit in self.trail_at_level(lvl) {
+        if !on_trail.insert(lit.var()) {
+          return Err(format!("{} is on the trail twice", lit));
+        }
+        if lit.assn(&self.assignments) != Some(true) || self.levels[lit.var()] != Some(lvl) {
+          return Err(format!(
+            "{} on the trail at level {} is assigned {:?} at level {:?}",
+            lit,
+            lvl,
+            self.assignments[lit.var()],
+            self.levels[lit.var()]
+          ));
+        }
+        let reason = match &self.causes[lit.var()] {
+          None => continue,
+          Some(reason) => reason,
+        };
+        let implies = reason
+          .literals
+          .iter()
+          .all(|&other| other == lit || other.assn(&self.assignments) == Some(false));
+        if !implies || !reason.literals.contains(&lit) {
+          return Err(format!("{} does not imply {}", **reason, lit));
+        }
+      }
+    }
+    let assigned = self
+      .assignments
+      .iter()
+      .filter(|assn| assn.is_some())
+      .count();
+    if on_trail.len() != assigned {
+      return Err(format!(
+        "{} variables are assigned but {} are on the trail",
+        assigned,
+        on_trail.len()
+      ));
+    }
+    for (raw, watches) in self.watch_list.occurrences.iter().enumerate() {
+      let lit = Literal::from(raw as u32);
+      for (clause, &other) in watches.iter() {
+        let paired = self.watch_list.occurrences[other.raw() as usize].get(clause) == Some(&lit);
+        let contained = clause.literals.contains(&lit) && clause.literals.contains(&other);
+        if lit == other || !paired || !contained {
+          return Err(format!(
+            "{} is watched by {} and {} inconsistently",
+            **clause, lit, other
+          ));
+        }
+      }
+    }
+    Ok(())
+  }
+  /// Applies the search parameters in config to this solver.
+  /// Should be called before solving, as it resets the restart schedule and saved phases.
+  /// Panics if learnt clauses would be reduced at a fixed interval of 0 conflicts.
+  pub fn configure(&mut self, config: &SolverConfig) {
+    assert_ne!(
+      config.reduce_policy,
+      ReducePolicy::FixedInterval(0),
+      "learnt clauses must be reduced every few conflicts"
+    );
+    self.config = *config;
+    self.rng = StdRng::seed_from_u64(config.seed);
+    self.restart_state = RestartState::new(config.restart_base, config.restart_inc);
+    self.var_state.decay_rate = config.var_decay;
+    self.reset_phases(config.phase);
+  }
+  /// Hints that var should be decided as val, by setting its saved phase.
+  /// Hints only bias which polarity is tried first, so they never affect whether a result is
+  /// correct. Phase saving and rephasing overwrite them as the search goes on, and they are
+  /// ignored when phases are random without phase saving.
+  pub fn set_phase_hint(&mut self, var: usize, val: bool) { self.polarities[var] = val; }
+  /// Sets the phase hint of every variable with Some value in phases, such as a previously
+  /// found model, leaving the others as they are. See `set_phase_hint`.
+  pub fn set_phases(&mut self, phases: &[Option<bool>]) {
+    assert!(phases.len() <= self.polarities.len());
+    for (var, &phase) in phases.iter().enumerate() {
+      if let Some(val) = phase {
+        self.set_phase_hint(var, val);
+      }
+    }
+  }
+  /// Overwrites the saved phase of every variable with phase
+  fn reset_phases(&mut self, phase: Phase) {
+    let rng = &mut self.rng;
+    self.polarities.iter_mut().for_each(|p| {
+      *p = match phase {
+        Phase::False => false,
+        Phase::True => true,
+        Phase::Random => rng.gen(),
+      }
+    });
+  }
+  /// Resets the saved phases to the next phase of the rephase policy if the rephase interval
+  /// has passed since the last rephase.
+  fn maybe_rephase(&mut self) {
+    let interval = match self.config.rephase_interval {
+      Some(interval) if interval > 0 && !self.config.rephase_policy.is_empty() => interval,
+      _ => return,
+    };
+    let restarts = self.stats.restarts;
+    if !restarts.is_multiple_of(interval) {
+      return;
+    }
+    let policy = self.config.rephase_policy;
+    let phase = policy[(restarts / interval - 1) as usize % policy.len()];
+    self.reset_phases(phase);
+  }
+  /// Writes an LRAT proof of every clause learnt from now on to w, ending with the empty
+  /// clause if the formula is found to be UNSAT. Initial clauses are numbered from 1 in the
+  /// order they were parsed, and learnt clauses by their `Clause::id`. Ids of replicas
+  /// interleave, so lines written by several solvers may not be in increasing order of id.
+  /// The proof does not cover preprocessing, so it is only valid if no pure literals, subsumed
+  /// clauses, strengthened clauses, or eliminated variables were removed beforehand.
+  pub fn enable_lrat<W: Write + Send + 'static>(&mut self, w: W) {
+    let lrat = Lrat::new(Box::new(w));
+    self.lrat = Some(Arc::new(Mutex::new(lrat)));
+    // initial units may have already conflicted, so propagate them again to prove it
+    if self.level == 0 && self.db.get_solution() == DbSolution::Unsat {
+      self.rewatch();
+    }
+  }
+  /// Records what every clause learnt from now on is derived from, so that once the formula is
+  /// found to be UNSAT the initial clauses used to refute it can be found with
+  /// `unsat_core_clauses`. Replicas created afterwards share the record.
+  /// Like LRAT proofs this does not cover preprocessing or clauses added with `add_clause`.
+  pub fn track_unsat_core(&mut self) {
+    self.derivations = Some(Arc::new(Mutex::new(Derivations::default())));
+    // initial units may have already conflicted, so propagate them again to record it
+    if self.level == 0 && self.db.get_solution() == DbSolution::Unsat {
+      self.rewatch();
+    }
+  }
+  /// Returns the initial clauses which the refutation of the formula was derived from, which
+  /// are UNSAT by themselves. Empty unless the UNSAT core was tracked with `track_unsat_core`
+  /// and a solver sharing it refuted the formula.
+  pub fn unsat_core_clauses(&self) -> Vec<ClauseRef> {
+    let core = match &self.derivations {
+      None => return vec![],
+      Some(derivations) => derivations.lock().unwrap().core(),
+    };
+    let core = match core {
+      None => return vec![],
+      Some(core) => core,
+    };
+    self
+      .db
+      .initial_iter()
+      .filter(|clause| core.contains(&clause.id))
+      .collect()
+  }
+  /// Stops writing the LRAT proof and flushes it, returning the first error from writing it.
+  pub fn finish_lrat(&mut self) -> io::Result<()> {
+    match self.lrat.take() {
+      None => Ok(()),
+      Some(lrat) => lrat.lock().unwrap().finish(),
+    }
+  }
+  /// Returns the id of the next clause derived by this solver. Ids continue after the parsed
+  /// clauses and interleave between replicas, so they only depend on which solver derived a
+  /// clause and how many it derived before, and not on the timing of other threads.
+  fn next_clause_id(&mut self) -> u64 {
+    let id = self.next_id;
+    self.next_id += self.db.num_solvers() as u64;
+    self.db.issued_clause_id(id);
+    id
+  }
+  /// Numbers the clause of literals derived from the conflict, and adds it to the LRAT proof
+  /// and the UNSAT core record if they are kept. Returns its id.
+  /// Must be called before backtracking, while the conflict is still falsified.
+  fn prove(&mut self, literals: &[Literal], conflict: &ClauseRef) -> u64 {
+    let id = self.next_clause_id();
+    if self.lrat.is_none() && self.derivations.is_none() {
+      return id;
+    }
+    let learnt = literals.iter().map(|lit| lit.var()).collect::<HashSet<_>>();
+    // Every variable not in the learnt clause which the conflict depends on is implied by its
+    // reason once the learnt literals are false
+    let mut implied = HashSet::default();
+    let mut stack = conflict
+      .literals
+      .iter()
+      .map(|lit| lit.var())
+      .collect::<Vec<_>>();
+    while let Some(var) = stack.pop() {
+      if learnt.contains(&var) || !implied.insert(var) {
+        continue;
+      }
+      if let Some(reason) = &self.causes[var] {
+        stack.extend(reason.literals.iter().map(|lit| lit.var()));
+      }
+    }
+    // reasons become unit in the order their variables were assigned
+    let mut hints = self
+      .assignment_trail
+      .iter()
+      .filter(|lit| implied.contains(&lit.var()))
+      .filter_map(|lit| self.causes[lit.var()].as_ref())
+      .map(|reason| reason.id)
+      .collect::<Vec<_>>();
+    hints.push(conflict.id);
+    if let Some(lrat) = &self.lrat {
+      lrat.lock().unwrap().add(id, literals, &hints);
+    }
+    if let Some(derivations) = &self.derivations {
+      derivations.lock().unwrap().add(id, literals, hints);
+    }
+    id
+  }
+  /// Calls f with this solver's statistics every every_conflicts conflicts during solve.
+  pub fn set_progress(&mut self, every_conflicts: u64, f: ProgressHook) {
+    self.progress = Some(Progress {
+      every_conflicts,
+      hook: Arc::new(Mutex::new(f)),
+    });
+  }
+  fn report_progress(&self) {
+    let progress = match &self.progress {
+      Some(progress) if progress.every_conflicts > 0 => progress,
+      _ => return,
+    };
+    if (self.stats.clauses_learned as u64).is_multiple_of(progress.every_conflicts) {
+      // A panicking hook on another replica should not stop this one from reporting
+      let mut hook = progress.hook.lock().unwrap_or_else(|e| e.into_inner());
+      (*hook)(&self.stats);
+    }
+  }
+  /// Returns the search parameters this solver is using.
+  pub fn config(&self) -> &SolverConfig { &self.config }
+  /// Returns the restart schedule of this solver, along with how far along it the solver is
+  pub fn restart_state(&self) -> &RestartState { &self.restart_state }
+  /// Sets the restart schedule of this solver, restarting the luby sequence.
+  pub fn set_restart_schedule(&mut self, base: u64, inc: u64) {
+    self.config.restart_base = base;
+    self.config.restart_inc = inc;
+    self.restart_state = RestartState::new(base, inc);
+  }
+  /// Sets the initial learnt clause limit and its growth, used by the next call to solve.
+  pub fn set_learntsize(&mut self, factor: f64, inc: f64) {
+    self.config.learntsize_factor = factor;
+    self.config.learntsize_inc = inc;
+  }
+  /// Replicates this one solver into n solvers with the same state, which share its database.
+  /// A solver can be replicated again once all of its previous replicas are dropped, but only
+  /// into at least as many solvers as before. Replicas number the clauses they derive after
+  /// every clause derived so far, including by dropped replicas.
+  pub fn replicate(mut self, n: usize) -> Result<Vec<Self>, ReplicateError> {
+    let db = Arc::get_mut(&mut self.db).ok_or(ReplicateError::DatabaseShared)?;
+    db.resize_to(n)
+      .map_err(|err| ReplicateError::AlreadyReplicated {
+        solvers: err.solvers,
+      })?;
+    let next_id = db.next_clause_id();
+    self.latest_clauses.resize(n, 0);
+    let mut replicas = (0..n)
+      .filter(|&id| id != self.id)
+      .map(|id| {
+        let mut out = self.clone();
+        out.id = id;
+        out.next_id = next_id + id as u64;
+        out
+      })
+      .collect::<Vec<_>>();
+    self.next_id = next_id + self.id as u64;
     replicas.push(self);
-    Some(replicas)
+    Ok(replicas)
+  }
+  /// Replicates this solver into n solvers, assigning each a configuration from configs in
+  /// turn, cycling if there are fewer configs than solvers.
+  /// Diverse search parameters combined with clause sharing through the database is the
+  /// intended way to solve in parallel, as identical replicas would mostly repeat each other's
+  /// work. Fails in the same cases as `replicate`.
+  pub fn replicate_diverse(
+    self,
+    n: usize,
+    configs: &[SolverConfig],
+  ) -> Result<Vec<Self>, ReplicateError> {
+    let mut replicas = self.replicate(n)?;
+    replicas
+      .iter_mut()
+      .zip(configs.iter().cycle())
+      .for_each(|(solver, config)| solver.configure(config));
+    Ok(replicas)
   }
 
   /// checks whether a literal in a conflict clause is redundant
   #[allow(dead_code)]
   fn lit_redundant(&self, lit: Literal, seen: &mut HashMap<usize, SeenState>) -> bool {
-    let cause = self.reason(lit.var()).unwrap();
+    let cause = self.cause(lit.var()).unwrap();
     let literals = cause.literals.iter().filter(|lit| {
       self
         .reason(lit.var())
-        .map_or(true, |reason| !Arc::ptr_eq(&reason.inner, &cause.inner))
+        .is_none_or(|reason| !Arc::ptr_eq(&reason.inner, &cause.inner))
     });
 
     for lit in literals {
       let redundant = self.levels[lit.var()] == Some(0)
-        || seen.get(&lit.var()).map_or(false, |&ss| {
-          ss == SeenState::Source || ss == SeenState::Redundant
-        });
+        || seen
+          .get(&lit.var())
+          .is_some_and(|&ss| ss == SeenState::Source || ss == SeenState::Redundant);
       if redundant {
         continue;
       }
-      let required = self.reason(lit.var()).is_none()
+      let required = self.cause(lit.var()).is_none()
         || seen
           .get(&lit.var())
-          .map_or(false, |&ss| ss == SeenState::Required)
+          .is_some_and(|&ss| ss == SeenState::Required)
         || !self.lit_redundant(*lit, seen);
       if required {
         seen.entry(lit.var()).or_insert(SeenState::Required);
@@ -413,9 +2050,1387 @@ impl Solver {
   }
 }
 
+/// The outcome of solving a formula.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveResult {
+  /// A satisfying assignment, indexed by variable
+  Sat(Vec<bool>),
+  Unsat,
+  /// Solving was stopped before an answer was found
+  Unknown,
+}
+
+/// The outcome of solving along with the statistics of the solver which solved it, see
+/// `Solver::run`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolveReport {
+  pub result: SolveResult,
+  pub stats: Stats,
+  /// whether this solver found the result, instead of another solver sharing its database
+  pub found_by_self: bool,
+}
+
+impl SolveResult {
+  pub fn is_sat(&self) -> bool { matches!(self, SolveResult::Sat(_)) }
+  pub fn is_unsat(&self) -> bool { *self == SolveResult::Unsat }
+  /// Returns the satisfying assignment if there is one
+  pub fn model(self) -> Option<Vec<bool>> {
+    match self {
+      SolveResult::Sat(model) => Some(model),
+      _ => None,
+    }
+  }
+}
+
+/// A clause learnt from a conflict, along with how it was derived
+#[derive(Debug)]
+pub struct ConflictAnalysis {
+  /// The learnt clause, with its literals sorted
+  pub learnt: Clause,
+  /// The level at which the learnt clause becomes unit
+  pub backtrack_level: usize,
+  /// The conflicting clause followed by each reason it was resolved with, in the order they
+  /// were resolved. Reasons only used to minimize the learnt clause are not included.
+  pub antecedents: Vec<ClauseRef>,
+  /// The number of resolution steps before minimization
+  pub resolutions: usize,
+}
+
+/// An assigned literal in an implication graph
+#[derive(Debug, Clone)]
+pub struct ImplNode {
+  /// The literal, which is true
+  pub lit: Literal,
+  /// The decision level the literal was assigned at
+  pub level: usize,
+  /// The clause which implied the literal, or none if it was decided
+  pub cause: Option<ClauseRef>,
+}
+
+/// The implication graph of a trail leading to a conflict, see `Solver::implication_graph`
+#[derive(Debug, Clone)]
+pub struct ImplGraph {
+  /// Every assigned literal, in the order they were assigned
+  pub nodes: Vec<ImplNode>,
+  /// Edges from a literal to a literal it implied along with others
+  pub edges: Vec<(Literal, Literal)>,
+  /// The assigned literals which together falsify the conflicting clause
+  pub conflict: Vec<Literal>,
+}
+
+/// Converts a solution stored in a database, which is unknown if it is undecided.
+impl From<DbSolution> for SolveResult {
+  fn from(solution: DbSolution) -> Self {
+    match solution {
+      DbSolution::Undecided => SolveResult::Unknown,
+      DbSolution::Unsat => SolveResult::Unsat,
+      DbSolution::Sat(model) => SolveResult::Sat(model),
+    }
+  }
+}
+
+/// Reasons a solver could not be replicated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicateError {
+  /// The database is still shared with other solvers, so it cannot be resized.
+  DatabaseShared,
+  /// The database was already replicated into more solvers than requested.
+  AlreadyReplicated { solvers: usize },
+}
+
+impl fmt::Display for ReplicateError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ReplicateError::DatabaseShared => write!(f, "database is shared with other solvers"),
+      ReplicateError::AlreadyReplicated { solvers } => {
+        write!(
+          f,
+          "database was already replicated into {} solvers",
+          solvers
+        )
+      },
+    }
+  }
+}
+
+impl std::error::Error for ReplicateError {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum SeenState {
   Source,
   Redundant,
   Required,
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  #[test]
+  fn import_same_clause_twice() {
+    let mut solvers = Solver::from_dimacs("src/bin/data/small/sample.cnf")
+      .unwrap()
+      .replicate(2)
+      .unwrap();
+    let mut dst = solvers.pop().unwrap();
+    let src = solvers.pop().unwrap();
+    // implied by the initial clause (1 | -3)
+    let learnt = ClauseRef::from(Clause::from(vec![
+      Literal::from(1),
+      Literal::from(2),
+      Literal::from(-3),
+    ]));
+    src.db.add_learnts(src.id(), &mut vec![learnt.clone()]);
+    let mut transfers = vec![];
+    dst.db.since(&mut transfers, &mut dst.latest_clauses);
+    assert_eq!(transfers, vec![learnt.clone()]);
+    let watches = |solver: &Solver| {
+      solver
+        .watch_list
+        .occurrences
+        .iter()
+        .filter(|watches| watches.contains_key(&learnt))
+        .count()
+    };
+    assert_eq!(dst.import(learnt.clone()), None);
+    assert_eq!(watches(&dst), 2);
+    assert_eq!(dst.import(learnt.clone()), None);
+    assert_eq!(watches(&dst), 2);
+    assert_eq!(dst.imported.len(), 1);
+  }
+  #[test]
+  fn transfers_share_storage() {
+    let mut solvers = Solver::from_dimacs("src/bin/data/small/sample.cnf")
+      .unwrap()
+      .replicate(2)
+      .unwrap();
+    let mut dst = solvers.pop().unwrap();
+    let src = solvers.pop().unwrap();
+    let learnt = ClauseRef::from(Clause::from(vec![
+      Literal::from(1),
+      Literal::from(2),
+      Literal::from(-3),
+    ]));
+    src.db.add_learnts(src.id(), &mut vec![learnt.clone()]);
+    let mut transfers = vec![];
+    dst.db.since(&mut transfers, &mut dst.latest_clauses);
+    assert!(Arc::ptr_eq(&transfers[0].inner, &learnt.inner));
+    assert_eq!(dst.import(transfers.pop().unwrap()), None);
+    let watched = dst.watch_list.learnt_clauses();
+    assert_eq!(watched.len(), 1);
+    for cref in watched {
+      assert!(Arc::ptr_eq(&cref.inner, &learnt.inner));
+      assert_eq!(cref.literals.as_ptr(), learnt.literals.as_ptr());
+    }
+  }
+  #[test]
+  fn import_activities() {
+    let path = "src/bin/data/med_unsat/pret150_25.cnf";
+    let mut solver = Solver::from_dimacs(path).unwrap();
+    let initial = solver.export_activities();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    let activities = solver.export_activities();
+    assert_ne!(activities, initial);
+    solver.reset();
+    assert_eq!(solver.export_activities(), initial);
+    solver.import_activities(&activities);
+    assert_eq!(solver.export_activities(), activities);
+    let mut first = activities.iter().enumerate().collect::<Vec<_>>();
+    first.sort_by(|(i, a), (j, b)| b.partial_cmp(a).unwrap().then(i.cmp(j)));
+    assert_eq!(solver.choose_lit().map(|lit| lit.var()), Some(first[0].0));
+  }
+  #[test]
+  fn walksat() {
+    let mut solver = Solver::from_dimacs("src/bin/data/jnh/jnh1.cnf").unwrap();
+    let model = solver.walksat(10_000, 10, 0.5).unwrap();
+    assert!(solver.verify(&model));
+    let mut again = Solver::from_dimacs("src/bin/data/jnh/jnh1.cnf").unwrap();
+    assert_eq!(again.walksat(10_000, 10, 0.5), Some(model));
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    assert_eq!(solver.walksat(1_000, 2, 0.5), None);
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+  }
+  #[test]
+  #[should_panic(expected = "noise must be a probability")]
+  fn walksat_noise_out_of_range() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    solver.walksat(100, 1, 1.5);
+  }
+  #[test]
+  fn minimize_model() {
+    let solver = Solver::from_clauses(vec![vec![1, 2], vec![-1, 3], vec![2, 3, 4]], 5);
+    let minimal = solver.minimize_model(&[true, true, true, false, true]);
+    // 1 is only needed to satisfy itself, and then 2 and 3 satisfy every clause
+    assert_eq!(minimal, vec![None, Some(true), Some(true), None, None]);
+    let mut solver = Solver::from_dimacs("src/bin/data/jnh/jnh1.cnf").unwrap();
+    let model = solver.solve().model().unwrap();
+    let minimal = solver.minimize_model(&model);
+    assert!(minimal.iter().any(Option::is_none));
+    for clause in solver.db.initial() {
+      assert!(clause
+        .literals
+        .iter()
+        .any(|lit| lit.assn(&minimal) == Some(true)));
+    }
+    // every variable left is needed by some clause
+    for var in (0..minimal.len()).filter(|&var| minimal[var].is_some()) {
+      let mut fewer = minimal.clone();
+      fewer[var] = None;
+      assert!(solver.db.initial().iter().any(|clause| clause
+        .literals
+        .iter()
+        .all(|lit| lit.assn(&fewer) != Some(true))));
+    }
+  }
+  #[test]
+  fn keep_all_learnt() {
+    // learnt clauses still in the solver, either watched or as units at level 0
+    let kept = |solver: &Solver| {
+      let watched = solver.watch_list.learnt_clauses();
+      let units = solver
+        .trail_at_level(0)
+        .iter()
+        .filter_map(|lit| solver.causes[lit.var()].as_ref())
+        .filter(|cref| !cref.initial && cref.literals.len() == 1)
+        .count();
+      watched.len() + units
+    };
+    let path = "src/bin/data/med_unsat/pret150_25.cnf";
+    let mut solver = Solver::from_dimacs(path).unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert!(kept(&solver) < solver.stats.learnt_clauses);
+    let mut solver = SolverBuilder::new()
+      .keep_all_learnt(true)
+      .build_from_dimacs(path)
+      .unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    // the final conflict is counted but refutes the formula instead of being learnt
+    assert_eq!(
+      solver.stats.learnt_clauses,
+      solver.stats.clauses_learned - 1
+    );
+    assert_eq!(kept(&solver), solver.stats.learnt_clauses);
+  }
+  #[test]
+  fn reduce_policy() {
+    let path = "src/bin/data/med_unsat/pret150_25.cnf";
+    let learnts = |policy| {
+      let mut solver = SolverBuilder::new()
+        .reduce_policy(policy)
+        .build_from_dimacs(path)
+        .unwrap();
+      assert_eq!(solver.solve(), SolveResult::Unsat);
+      let watched = solver.watch_list.learnt_clauses();
+      (watched.len(), solver.stats.clauses_learned)
+    };
+    // never reduced, so only clauses satisfied at level 0 were deleted
+    let (never, conflicts) = learnts(ReducePolicy::FixedInterval(u64::MAX));
+    assert!(never < conflicts);
+    let (often, _) = learnts(ReducePolicy::FixedInterval(100));
+    assert!(often < never);
+  }
+  #[test]
+  #[should_panic(expected = "reduced every few conflicts")]
+  fn reduce_every_zero_conflicts() {
+    SolverBuilder::new().reduce_policy(ReducePolicy::FixedInterval(0));
+  }
+  #[test]
+  fn statistics() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    assert_eq!(solver.statistics().clauses_learned, 0);
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    let snapshot = solver.statistics();
+    assert_eq!(snapshot.clauses_learned, solver.stats.clauses_learned);
+    assert_eq!(snapshot.propagations, solver.stats.propogations);
+    assert_eq!(snapshot.max_level, solver.stats.max_level());
+    assert!(snapshot.elapsed <= solver.statistics().elapsed);
+    solver.reset_stats();
+    assert_eq!(solver.statistics().clauses_learned, 0);
+  }
+  #[test]
+  fn found_by_self() {
+    let mut solvers = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf")
+      .unwrap()
+      .replicate(2)
+      .unwrap();
+    assert!(solvers[0].solve().is_sat());
+    assert!(solvers[0].found_by_self());
+    let report = solvers[1].run();
+    assert!(report.result.is_sat());
+    assert!(!report.found_by_self);
+    assert!(!solvers[1].found_by_self());
+  }
+  #[test]
+  fn run() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    let report = solver.run();
+    assert_eq!(report.result, SolveResult::Unsat);
+    assert!(report.stats.clauses_learned > 0);
+    assert_eq!(report.stats, solver.stats);
+  }
+  #[test]
+  fn with_database() {
+    let (clauses, max_var) =
+      crate::dimacs::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    let mut db = ClauseDatabase::new(max_var, clauses);
+    db.reserve_solvers(1);
+    let db = Arc::new(db);
+    let mut first = Solver::with_database(db.clone());
+    let second = Solver::with_database(db.clone());
+    assert_eq!((first.id(), second.id()), (0, 1));
+    assert_eq!(second.latest_clauses.len(), 2);
+    let model = first.solve().model().unwrap();
+    assert!(db.verify(&model));
+    // the other solver shares the solution through the database
+    assert_eq!(second.finished(), Some(SolveResult::Sat(model)));
+    let full = std::panic::catch_unwind(|| Solver::with_database(db));
+    assert!(full.is_err());
+  }
+  #[test]
+  fn remove_satisfied_learnts() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/sample.cnf").unwrap();
+    // implied by the initial clause (1 | -3)
+    let learnt = ClauseRef::from(Clause::from(vec![
+      Literal::from(1),
+      Literal::from(2),
+      Literal::from(-3),
+    ]));
+    solver
+      .db
+      .add_learnts(solver.id(), &mut vec![learnt.clone()]);
+    // other solvers which imported the clause may keep it alive
+    assert_eq!(solver.import(learnt.clone()), None);
+    assert_eq!(solver.with(Literal::from(2), None), None);
+    assert_eq!(solver.db.stats().live_learnt, 1);
+    solver.remove_satisfied();
+    let stats = solver.db.stats();
+    assert_eq!((stats.live_learnt, stats.deleted_learnt), (0, 1));
+    assert!(!solver.db.learnt_over_budget());
+  }
+  #[test]
+  fn unsat_by_initial_units() {
+    for f in &["sample_no.cnf", "unsat_mini.cnf"] {
+      let mut solver = Solver::from_dimacs(format!("src/bin/data/small/{}", f)).unwrap();
+      assert_eq!(solver.db.get_solution(), DbSolution::Unsat);
+      assert_eq!(solver.solve(), SolveResult::Unsat);
+    }
+  }
+  #[test]
+  fn decided_by_units() {
+    let (clauses, max_var) = crate::dimacs::from_str("p cnf 3 3\n1 0\n-2 0\n3 0").unwrap();
+    let mut solver = Solver::new(max_var, clauses, &SolverConfig::default());
+    let model = vec![true, false, true];
+    assert_eq!(solver.db.get_solution(), DbSolution::Sat(model.clone()));
+    assert_eq!(solver.solve(), SolveResult::Sat(model));
+    assert_eq!(solver.stats.max_level(), 0);
+    let (clauses, max_var) = crate::dimacs::from_str("p cnf 2 3\n1 0\n-2 0\n-1 2 0").unwrap();
+    let solver = Solver::new(max_var, clauses, &SolverConfig::default());
+    assert_eq!(solver.db.get_solution(), DbSolution::Unsat);
+  }
+  #[test]
+  fn formula_without_clauses() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/empty.cnf").unwrap();
+    assert_eq!(solver.solve(), SolveResult::Sat(vec![]));
+    let mut solver = Solver::from_dimacs("src/bin/data/small/no_clauses.cnf").unwrap();
+    assert_eq!(solver.solve(), SolveResult::Sat(vec![false; 3]));
+    assert_eq!(solver.stats.propogations, 0);
+  }
+  #[test]
+  fn pure_literals() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/sample.cnf").unwrap();
+    // 2 is pure, and once it is set both 1 and !3 become pure
+    assert_eq!(solver.num_free(), 3);
+    assert_eq!(solver.eliminate_pure_literals(), 3);
+    assert_eq!(solver.num_free(), 0);
+    let sol = solver.solve().model().unwrap();
+    assert!(solver.verify(&sol));
+    assert_eq!(solver.stats.clauses_learned, 0);
+    solver.reset();
+    assert_eq!(solver.num_free(), 3);
+    assert!(solver.pure_literals.is_empty());
+  }
+  #[test]
+  fn add_clause_after_pure_literals() {
+    let clause = |lits: &[i32]| lits.iter().map(|&lit| Literal::from(lit)).collect();
+    let mut solver = Solver::from_clauses(vec![vec![1, 2]], 2);
+    assert_eq!(solver.eliminate_pure_literals(), 2);
+    assert!(solver.add_clause(clause(&[-1, -2])));
+    assert!(solver.add_clause(clause(&[-1, 2])));
+    assert_eq!(solver.solve(), SolveResult::Sat(vec![false, true]));
+    // every model is found, not only those agreeing with the pure literals
+    let mut solver = Solver::from_clauses(vec![vec![1, 2]], 2);
+    solver.eliminate_pure_literals();
+    let mut models = solver.solve_all(None);
+    models.sort();
+    assert_eq!(
+      models,
+      vec![vec![false, true], vec![true, false], vec![true, true]]
+    );
+  }
+  #[test]
+  fn subsumed_clauses() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/subsumed.cnf").unwrap();
+    assert_eq!(solver.subsume(), Some(2));
+    assert_eq!(solver.db.initial().len(), 2);
+    assert_eq!(solver.stats.subsumed_clauses, 2);
+    let sol = solver.solve().model().unwrap();
+    assert!(solver.verify(&sol));
+  }
+  #[test]
+  fn variable_elimination() {
+    let original = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    assert!(solver.bounded_variable_elimination(0).unwrap() > 0);
+    let sol = solver.solve().model().unwrap();
+    assert!(original.verify(&sol));
+    assert!(solver.verify(&sol));
+    assert!(!solver.verify(&sol[1..]));
+    // overwriting eliminated variables still reconstructs a model
+    let mut partial = sol.iter().copied().map(Some).collect::<Vec<_>>();
+    for var in solver.db.model_extension().vars() {
+      partial[var] = Some(!sol[var]);
+    }
+    assert!(solver.verify(&solver.reconstruct_model(&partial)));
+
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    assert!(solver.bounded_variable_elimination(0).unwrap() > 0);
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+  }
+  #[test]
+  fn replicate_again() {
+    let solver = Solver::from_dimacs("src/bin/data/small/sample.cnf").unwrap();
+    let mut solvers = solver.replicate(2).unwrap();
+    let other = solvers.pop().unwrap();
+    assert_eq!(
+      solvers.pop().unwrap().replicate(3).err(),
+      Some(ReplicateError::DatabaseShared)
+    );
+    let solvers = other.replicate(4).unwrap();
+    let mut ids = solvers.iter().map(Solver::id).collect::<Vec<_>>();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![0, 1, 2, 3]);
+    assert_eq!(solvers[0].db.num_solvers(), 4);
+    let solver = solvers.into_iter().next().unwrap();
+    assert_eq!(
+      solver.replicate(2).err(),
+      Some(ReplicateError::AlreadyReplicated { solvers: 4 })
+    );
+  }
+  #[test]
+  fn random_phase_is_seeded() {
+    let builder = SolverBuilder::new().phase(Phase::Random).seed(7);
+    let a = builder
+      .build_from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf")
+      .unwrap();
+    let b = builder
+      .build_from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf")
+      .unwrap();
+    assert_eq!(a.polarities, b.polarities);
+    assert!(a.polarities.iter().any(|&p| p) && a.polarities.iter().any(|&p| !p));
+  }
+  #[test]
+  fn random_decisions_are_seeded() {
+    let builder = SolverBuilder::new()
+      .phase(Phase::Random)
+      .phase_saving(false)
+      .seed(7);
+    let decisions = || {
+      let mut solver = builder
+        .build_from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf")
+        .unwrap();
+      (0..32)
+        .map(|_| solver.choose_lit().unwrap())
+        .collect::<Vec<_>>()
+    };
+    let lits = decisions();
+    assert_eq!(lits, decisions());
+    assert!(lits.iter().any(|lit| lit.negated()) && lits.iter().any(|lit| !lit.negated()));
+  }
+  #[test]
+  fn per_replica_schedules() {
+    let solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    let mut solvers = solver.replicate(2).unwrap();
+    solvers[0].set_restart_schedule(50, 3);
+    solvers[0].set_learntsize(0.5, 1.1);
+    assert_eq!(solvers[0].restart_state, RestartState::new(50, 3));
+    assert_eq!(solvers[0].config().learntsize_factor, 0.5);
+    assert_eq!(
+      solvers[1].restart_state,
+      RestartState::new(RESTART_BASE, RESTART_INC)
+    );
+    assert_eq!(solvers[1].config().learntsize_factor, LEARNTSIZE_FACTOR);
+    for mut solver in solvers {
+      let sol = solver.solve().model().unwrap();
+      assert!(solver.verify(&sol));
+    }
+  }
+  #[test]
+  fn progress_hook() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let hook_calls = calls.clone();
+    solver.set_progress(
+      10,
+      Box::new(move |stats| {
+        assert_eq!(stats.clauses_learned % 10, 0);
+        hook_calls.fetch_add(1, Ordering::Relaxed);
+      }),
+    );
+    solver.solve().model().unwrap();
+    assert_eq!(
+      calls.load(Ordering::Relaxed),
+      solver.stats.clauses_learned / 10
+    );
+  }
+  #[test]
+  fn reset_stats() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    let model = solver.solve().model().unwrap();
+    assert!(solver.stats.propogations > 0);
+    let started = solver.stats.start_time;
+    solver.reset_stats();
+    assert_eq!(solver.stats.propogations, 0);
+    assert_eq!(solver.stats.clauses_learned, 0);
+    assert_eq!(solver.stats.max_level(), 0);
+    assert!(solver.stats.start_time > started);
+    // only the work for the next query is counted
+    let blocking = model
+      .iter()
+      .enumerate()
+      .map(|(var, &val)| Literal::new(var as u32, val))
+      .collect();
+    assert!(solver.add_clause(blocking));
+    solver.solve();
+    assert!(solver.stats.propogations > 0);
+  }
+  #[test]
+  fn reset() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    let first = solver.solve().model().unwrap();
+    solver.reset();
+    assert_eq!(solver.db.get_solution(), DbSolution::Undecided);
+    assert_eq!(solver.level, 0);
+    assert!(solver.has_unassigned_vars());
+    assert_eq!(solver.stats.clauses_learned, 0);
+    let second = solver.solve().model().unwrap();
+    assert!(solver.verify(&first) && solver.verify(&second));
+
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    solver.reset();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+  }
+  #[test]
+  fn empty_learnt_clause() {
+    // the first conflict learns a unit, whose propagation conflicts at level 0
+    let mut solver = Solver::from_dimacs("src/bin/data/small/all_pairs.cnf").unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert_eq!(solver.level, 0);
+    assert_eq!(solver.db.get_solution(), DbSolution::Unsat);
+    let falsified = solver
+      .db
+      .iter()
+      .find(|clause| {
+        clause
+          .literals
+          .iter()
+          .all(|lit| lit.assn(&solver.assignments) == Some(false))
+      })
+      .unwrap();
+    assert_eq!(solver.analyze(&falsified), None);
+  }
+  #[test]
+  fn lrat_proof() {
+    let path = "src/bin/data/small/aim-100-1_6-no.cnf";
+    // unique to this process, so concurrent test runs do not overwrite each other's proofs
+    let proof_path =
+      std::env::temp_dir().join(format!("small_sat_lrat_proof_{}.lrat", std::process::id()));
+    let mut solver = Solver::from_dimacs(path).unwrap();
+    solver.enable_lrat(std::fs::File::create(&proof_path).unwrap());
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    solver.finish_lrat().unwrap();
+    let to_dimacs = |lit: &Literal| (lit.var() as i64 + 1) * if lit.negated() { -1 } else { 1 };
+    let (initial, _) = crate::dimacs::from_dimacs(path).unwrap();
+    let mut clauses = initial
+      .iter()
+      .enumerate()
+      .map(|(i, clause)| {
+        (
+          i as u64 + 1,
+          clause.literals.iter().map(to_dimacs).collect(),
+        )
+      })
+      .collect::<HashMap<u64, Vec<i64>>>();
+    let proof = std::fs::read_to_string(&proof_path).unwrap();
+    std::fs::remove_file(&proof_path).unwrap();
+    let mut derived_empty = false;
+    for line in proof.lines() {
+      let nums = line
+        .split_whitespace()
+        .map(|n| n.parse::<i64>().unwrap())
+        .collect::<Vec<_>>();
+      let end = nums.iter().skip(1).position(|&n| n == 0).unwrap() + 1;
+      let literals = nums[1..end].to_vec();
+      // every hint must be unit or conflicting under the negation of the derived clause
+      let mut true_lits = literals.iter().map(|lit| -lit).collect::<HashSet<_>>();
+      let conflict = nums[end + 1..nums.len() - 1].iter().any(|hint| {
+        let unassigned = clauses[&(*hint as u64)]
+          .iter()
+          .filter(|lit| !true_lits.contains(&-*lit))
+          .collect::<Vec<_>>();
+        assert!(unassigned.len() <= 1);
+        unassigned
+          .first()
+          .map(|&&lit| true_lits.insert(lit))
+          .is_none()
+      });
+      assert!(conflict);
+      derived_empty = literals.is_empty();
+      assert!(clauses.insert(nums[0] as u64, literals).is_none());
+    }
+    assert!(derived_empty);
+  }
+  #[test]
+  fn stop_when_solved() {
+    use std::{sync::mpsc::channel, thread, time::Duration};
+    let stop_with = |stop: fn(&ClauseDatabase), expected: SolveResult| {
+      // takes far longer than this test to solve
+      let solvers = Solver::from_dimacs("src/bin/data/large/f600.cnf")
+        .unwrap()
+        .replicate(2)
+        .unwrap();
+      let db = solvers[0].db.clone();
+      let (sender, receiver) = channel();
+      for mut solver in solvers {
+        let sender = sender.clone();
+        thread::spawn(move || sender.send(solver.solve()).unwrap());
+      }
+      thread::sleep(Duration::from_millis(100));
+      stop(&db);
+      // both solvers return soon after, with the result given to the database rather than
+      // their own, and the bound is loose enough for a loaded machine
+      for _ in 0..2 {
+        let result = receiver.recv_timeout(Duration::from_secs(10));
+        assert_eq!(result, Ok(expected.clone()));
+      }
+    };
+    stop_with(|db| db.add_solution(DbSolution::Unsat), SolveResult::Unsat);
+    stop_with(ClauseDatabase::terminate, SolveResult::Unknown);
+  }
+  #[test]
+  fn from_clauses() {
+    let mut solver = Solver::from_clauses(vec![vec![1, -2], vec![2, 3], vec![-1, -3]], 3);
+    let sol = solver.solve().model().unwrap();
+    assert!(solver.verify(&sol));
+    let mut solver =
+      Solver::from_clauses(vec![vec![1, 2], vec![-1, 2], vec![1, -2], vec![-1, -2]], 2);
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+  }
+  #[test]
+  fn unassigned_vars_decidable_after_backtrack() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    for _ in 0..5 {
+      solver.decide();
+      if solver.propagate().is_some() {
+        break;
+      }
+    }
+    assert!(solver.decision_level() > 1);
+    solver.backtrack_to(1);
+    solver.var_state.enable_unassigned(&solver.assignments);
+    let mut decidable = HashSet::default();
+    while let Some(var) = solver.var_state.take_highest_prio() {
+      decidable.insert(var);
+    }
+    assert!((0..solver.assignments.len())
+      .filter(|&var| solver.assignments[var].is_none())
+      .all(|var| decidable.contains(&var)));
+  }
+  #[test]
+  fn icnf_queries() {
+    use crate::dimacs::{from_icnf, IcnfLine};
+    let (lines, max_var) = from_icnf("src/bin/data/small/incremental.icnf").unwrap();
+    let mut solver = Solver::from_clauses(vec![], max_var);
+    let mut results = vec![];
+    for line in lines {
+      match line {
+        IcnfLine::Clause(lits) => {
+          assert!(solver.add_clause(lits.into_iter().map(Literal::from).collect()));
+        },
+        IcnfLine::Assume(lits) => {
+          lits
+            .iter()
+            .for_each(|&lit| solver.push_assumption(Literal::from(lit)));
+          results.push(solver.solve().is_sat());
+          lits
+            .iter()
+            .for_each(|_| assert!(solver.pop_assumption().is_some()));
+        },
+      }
+    }
+    assert_eq!(results, vec![false, true, false, true, false]);
+  }
+  #[test]
+  fn check_invariants() {
+    for path in &[
+      "src/bin/data/small/aim-50-1_6-yes.cnf",
+      "src/bin/data/small/aim-100-1_6-no.cnf",
+    ] {
+      let mut solver = Solver::from_dimacs(path).unwrap();
+      assert_eq!(solver.check_invariants(), Ok(()));
+      'search: while solver.decide().is_some() {
+        while let Some(conflict) = solver.propagate() {
+          assert_eq!(solver.check_invariants(), Ok(()));
+          if !solver.analyze_and_backtrack(&conflict) {
+            break 'search;
+          }
+        }
+        assert_eq!(solver.check_invariants(), Ok(()));
+      }
+    }
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    solver.decide().unwrap();
+    assert!(solver.propagate().is_none());
+    let var = solver.trail()[0].var();
+    solver.levels[var] = None;
+    assert!(solver.check_invariants().is_err());
+  }
+  #[test]
+  fn identical_decisions() {
+    let decisions = || {
+      let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+      let mut decisions = vec![];
+      while let Some(lit) = solver.decide() {
+        decisions.push(lit);
+        while let Some(conflict) = solver.propagate() {
+          assert!(solver.analyze_and_backtrack(&conflict));
+        }
+      }
+      decisions
+    };
+    let lits = decisions();
+    assert!(!lits.is_empty());
+    assert_eq!(lits, decisions());
+  }
+  #[test]
+  fn choose_lit_with_empty_queue() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    while solver.var_state.take_highest_prio().is_some() {}
+    let lit = solver.choose_lit().unwrap();
+    assert_eq!(lit.assn(&solver.assignments), None);
+    // with every variable assigned there is nothing to decide, rather than a panic
+    assert!(solver.solve().is_sat());
+    while solver.var_state.take_highest_prio().is_some() {}
+    assert_eq!(solver.choose_lit(), None);
+    assert_eq!(solver.decide(), None);
+  }
+  #[test]
+  fn watch_count() {
+    let solver = Solver::from_clauses(vec![vec![1, 2], vec![1, 3], vec![-2, -3, 1]], 3);
+    let watches = |lit: i32| solver.watch_count(Literal::from(lit));
+    assert_eq!(watches(1), 3);
+    assert_eq!(watches(-1), 0);
+    assert_eq!(watches(2) + watches(3) + watches(-2) + watches(-3), 3);
+    let histogram = solver.watch_histogram();
+    assert_eq!(histogram.len(), 3);
+    for (var, &count) in histogram.iter().enumerate() {
+      let var = var as i32 + 1;
+      assert_eq!(count, watches(var) + watches(-var));
+    }
+  }
+  #[test]
+  fn solve_all() {
+    let mut solver = Solver::from_clauses(vec![vec![1, 2], vec![-2, 3]], 3);
+    let mut models = solver.solve_all(None);
+    models.sort();
+    assert_eq!(
+      models,
+      vec![
+        vec![false, true, true],
+        vec![true, false, false],
+        vec![true, false, true],
+        vec![true, true, true],
+      ]
+    );
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    let mut solver = Solver::from_clauses(vec![vec![1, 2], vec![-2, 3]], 3);
+    assert_eq!(solver.solve_all(Some(2)).len(), 2);
+    let mut solver = Solver::from_clauses(vec![vec![1], vec![-1]], 1);
+    assert!(solver.solve_all(None).is_empty());
+    let path = "src/bin/data/small/aim-50-1_6-yes.cnf";
+    let models = Solver::from_dimacs(path).unwrap().solve_all(Some(20));
+    let original = Solver::from_dimacs(path).unwrap();
+    assert!(models.iter().all(|model| original.verify(model)));
+    assert_eq!(models.iter().collect::<HashSet<_>>().len(), models.len());
+  }
+  #[test]
+  fn max_level() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    assert_eq!(solver.stats.max_level(), 0);
+    solver.next_level();
+    solver.next_level();
+    solver.backtrack_to(0);
+    solver.next_level();
+    assert_eq!(solver.stats.max_level(), 2);
+  }
+  #[test]
+  fn solutions() {
+    let path = "src/bin/data/small/aim-50-1_6-yes.cnf";
+    let mut all = Solver::from_dimacs(path).unwrap().solve_all(None);
+    let mut solver = Solver::from_dimacs(path).unwrap();
+    let mut lazy = solver.solutions().collect::<Vec<_>>();
+    all.sort();
+    lazy.sort();
+    assert_eq!(lazy, all);
+    assert_eq!(solver.solutions().next(), None);
+    let mut solver = Solver::from_clauses(vec![vec![1, 2], vec![-2, 3]], 3);
+    assert_eq!(solver.solutions().take(3).count(), 3);
+    // models taken earlier stay blocked
+    assert_eq!(solver.solutions().count(), 1);
+  }
+  #[test]
+  fn solve_projected() {
+    let mut solver = Solver::from_clauses(vec![vec![1, 2], vec![-2, 3]], 3);
+    let mut projected = solver.solve_projected(&[1, 2], None);
+    projected.sort();
+    assert_eq!(
+      projected,
+      vec![
+        vec![None, Some(false), Some(false)],
+        vec![None, Some(false), Some(true)],
+        vec![None, Some(true), Some(true)],
+      ]
+    );
+    let mut solver = Solver::from_clauses(vec![vec![1, 2], vec![-2, 3]], 3);
+    assert_eq!(solver.solve_projected(&[], None), vec![vec![None; 3]]);
+  }
+  #[test]
+  fn assumptions() {
+    let mut solver = Solver::from_clauses(vec![vec![1, 2], vec![-2, 3]], 3);
+    solver.push_assumption(Literal::from(-1));
+    assert_eq!(solver.solve().model(), Some(vec![false, true, true]));
+    // the assumption still holds after a clause is added, which blocks the only model under it
+    assert!(solver.add_clause(vec![Literal::from(1), Literal::from(-2)]));
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert_eq!(solver.failed_assumptions(), &[Literal::from(-1)]);
+    assert_eq!(solver.db.get_solution(), DbSolution::Undecided);
+    assert_eq!(solver.pop_assumption(), Some(Literal::from(-1)));
+    assert_eq!(solver.pop_assumption(), None);
+    assert_eq!(solver.solve().model().map(|model| model[0]), Some(true));
+    // assumptions which need learning to refute, and ones consistent with a model
+    let path = "src/bin/data/small/aim-50-1_6-yes.cnf";
+    let mut solver = Solver::from_dimacs(path).unwrap();
+    let model = solver.solve().model().unwrap();
+    for (var, &val) in model.iter().enumerate().take(10) {
+      solver.push_assumption(Literal::new(var as u32, !val));
+    }
+    let assumed = solver.solve().model().unwrap();
+    assert!(solver.verify(&assumed));
+    assert_eq!(assumed[..10], model[..10]);
+    assert_eq!(solver.assumptions().len(), 10);
+    solver.push_assumption(Literal::new(0, model[0]));
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    // the first assumption on the same variable may be left out if learning fixed it at level 0
+    let failed = solver.failed_assumptions();
+    assert!(failed.contains(&Literal::new(0, model[0])));
+    assert!(failed.iter().all(|lit| solver.assumptions().contains(lit)));
+    solver.pop_assumption();
+    assert_eq!(solver.solve().model().unwrap()[..10], model[..10]);
+    // only the assumptions which propagation used are failed
+    let mut solver = Solver::from_clauses(vec![vec![-1, -2], vec![3, 4]], 4);
+    for lit in [3, 1, 2].iter() {
+      solver.push_assumption(Literal::from(*lit));
+    }
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert_eq!(
+      solver.failed_assumptions(),
+      &[Literal::from(1), Literal::from(2)]
+    );
+    // an assumption may contradict an eliminated pure literal
+    let mut solver = Solver::from_clauses(vec![vec![1, 2]], 2);
+    solver.eliminate_pure_literals();
+    solver.push_assumption(Literal::from(-1));
+    assert_eq!(solver.solve().model(), Some(vec![false, true]));
+  }
+  #[test]
+  fn count_models() {
+    let clauses = || vec![vec![1, 2], vec![-2, 3]];
+    assert_eq!(
+      Solver::from_clauses(clauses(), 3).count_models(None),
+      Some(4)
+    );
+    assert_eq!(
+      Solver::from_clauses(clauses(), 5).count_models(None),
+      Some(16)
+    );
+    assert_eq!(
+      Solver::from_clauses(vec![vec![1], vec![-1]], 1).count_models(None),
+      Some(0)
+    );
+    let mut solver = Solver::from_clauses(vec![vec![1, 2]], 2);
+    solver.eliminate_pure_literals();
+    assert_eq!(solver.count_models(None), Some(3));
+    // compare against brute force and enumeration on random formulas over 10 variables
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..20 {
+      let clauses = (0..30)
+        .map(|_| {
+          (0..3)
+            .map(|_| rng.gen_range(1, 11) * if rng.gen() { 1 } else { -1 })
+            .collect::<Vec<i32>>()
+        })
+        .collect::<Vec<_>>();
+      let brute_force = (0..1u32 << 10)
+        .filter(|bits| {
+          clauses.iter().all(|clause| {
+            clause
+              .iter()
+              .any(|&lit| (bits >> (lit.abs() - 1) & 1 == 1) == (lit > 0))
+          })
+        })
+        .count();
+      let mut solver = Solver::from_clauses(clauses.clone(), 10);
+      assert_eq!(solver.count_models(None), Some(brute_force as u128));
+      assert_eq!(solver.solve_all(None).len(), brute_force);
+    }
+    let path = "src/bin/data/small/aim-50-1_6-yes.cnf";
+    assert_eq!(
+      Solver::from_dimacs(path).unwrap().count_models(Some(1)),
+      None
+    );
+  }
+  #[test]
+  fn out_of_range_queries() {
+    let mut solver = Solver::from_clauses(vec![vec![1], vec![-1, 2]], 2);
+    let max_var = solver.num_vars();
+    assert_eq!(max_var, 2);
+    assert_eq!(solver.value(max_var - 1), Some(true));
+    assert_eq!(solver.level_of(max_var - 1), Some(0));
+    assert!(solver.reason(max_var - 1).is_some());
+    for var in [max_var, max_var + 1] {
+      assert_eq!(solver.value(var), None);
+      assert_eq!(solver.level_of(var), None);
+      assert!(solver.reason(var).is_none());
+    }
+    assert!(solver.solve().model().is_some());
+  }
+  #[test]
+  fn trail_by_level() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    assert_eq!(solver.decision_level(), 0);
+    let units = solver.trail().len();
+    assert_eq!(solver.trail_at_level(0).len(), units);
+    solver.next_level();
+    let lit = solver.choose_lit().unwrap();
+    assert_eq!(solver.with(lit, None), None);
+    assert_eq!(solver.decision_level(), 1);
+    assert_eq!(solver.trail_at_level(0).len(), units);
+    assert_eq!(solver.trail_at_level(1)[0], lit);
+    assert_eq!(solver.trail_at_level(1).len(), solver.trail().len() - units);
+    assert!(solver.trail_at_level(2).is_empty());
+  }
+  #[test]
+  fn step_wise() {
+    let step = |path| {
+      let mut solver = Solver::from_dimacs(path).unwrap();
+      while let Some(lit) = solver.decide() {
+        assert!(solver.trail().iter().all(|&assigned| assigned != lit));
+        while let Some(conflict) = solver.propagate() {
+          if !solver.analyze_and_backtrack(&conflict) {
+            return None;
+          }
+        }
+        // propagation must leave no clause unit
+        let assignments = &solver.assignments;
+        assert!(solver
+          .db
+          .iter()
+          .all(|c| c.unit_under(assignments).is_none()));
+      }
+      let sol = solver.final_assignments();
+      assert!(solver.verify(&sol));
+      Some(sol)
+    };
+    assert!(step("src/bin/data/small/aim-50-1_6-yes.cnf").is_some());
+    assert_eq!(step("src/bin/data/small/aim-100-1_6-no.cnf"), None);
+  }
+  #[test]
+  fn analyze_detailed() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    let conflict = loop {
+      solver.decide().unwrap();
+      if let Some(conflict) = solver.propagate() {
+        break conflict;
+      }
+    };
+    let analysis = solver.analyze_detailed(&conflict).unwrap();
+    assert_eq!(analysis.antecedents[0], conflict);
+    assert_eq!(analysis.resolutions, analysis.antecedents.len() - 1);
+    // every other antecedent is the reason for an assignment at the conflict level
+    assert!(analysis.antecedents[1..].iter().all(|reason| solver
+      .causes
+      .iter()
+      .zip(solver.levels.iter())
+      .any(|(cause, &lvl)| cause.as_ref() == Some(reason) && lvl == Some(solver.level))));
+    assert!(analysis.backtrack_level < solver.level);
+    assert!(!analysis.learnt.is_empty());
+  }
+  #[test]
+  fn clause_ids() {
+    let solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    // derived clauses are numbered after the parsed ones
+    let num_parsed = solver.db.next_clause_id() - 1;
+    let mut initial_ids = solver
+      .db
+      .initial_clauses
+      .iter()
+      .map(|c| c.id())
+      .collect::<Vec<_>>();
+    initial_ids.sort_unstable();
+    assert_eq!(initial_ids, (1..=num_parsed).collect::<Vec<_>>());
+    for mut solver in solver.replicate(2).unwrap() {
+      while solver.to_write.len() < 3 {
+        if solver.decide().is_none() {
+          break;
+        }
+        while let Some(conflict) = solver.propagate() {
+          assert!(solver.analyze_and_backtrack(&conflict));
+        }
+      }
+      // replicas take turns numbering clauses, so ids do not depend on thread timing
+      let ids = solver.to_write.iter().map(|c| c.id()).collect::<Vec<_>>();
+      let first = num_parsed + 1 + solver.id() as u64;
+      assert_eq!(ids, vec![first, first + 2, first + 4]);
+    }
+  }
+  #[test]
+  fn clause_ids_after_replicating_again() {
+    // derives clauses until n are waiting to be written, returning their ids
+    let derive = |solver: &mut Solver, n: usize| {
+      while solver.to_write.len() < n {
+        solver.decide().unwrap();
+        while let Some(conflict) = solver.propagate() {
+          assert!(solver.analyze_and_backtrack(&conflict));
+        }
+      }
+      solver.to_write.iter().map(|c| c.id()).collect::<Vec<_>>()
+    };
+    let solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    let mut solvers = solver.replicate(2).unwrap();
+    let mut kept = solvers.pop().unwrap();
+    let mut dropped = solvers.pop().unwrap();
+    // the dropped replica derives more clauses than the one kept
+    let mut issued = derive(&mut dropped, 6);
+    issued.extend(derive(&mut kept, 2));
+    drop(dropped);
+    let issued = issued.into_iter().collect::<HashSet<_>>();
+    let mut ids = HashSet::default();
+    for mut solver in kept.replicate(3).unwrap() {
+      // replicas start with the clauses the kept solver has not written yet
+      let written = solver.to_write.len();
+      for id in derive(&mut solver, written + 3).into_iter().skip(written) {
+        assert!(!issued.contains(&id));
+        assert!(ids.insert(id));
+      }
+    }
+  }
+  #[test]
+  fn implication_graph() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf").unwrap();
+    let conflict = loop {
+      solver.decide().unwrap();
+      if let Some(conflict) = solver.propagate() {
+        break conflict;
+      }
+    };
+    let graph = solver.implication_graph(&conflict);
+    assert_eq!(graph.nodes.len(), solver.assignment_trail.len());
+    let position = |lit: Literal| graph.nodes.iter().position(|node| node.lit == lit).unwrap();
+    // antecedents are assigned before the literals they imply
+    assert!(graph
+      .edges
+      .iter()
+      .all(|&(from, to)| position(from) < position(to)));
+    for node in &graph.nodes {
+      let implied_by = graph.edges.iter().filter(|(_, to)| *to == node.lit).count();
+      match &node.cause {
+        None => assert_eq!(implied_by, 0),
+        Some(cause) => assert_eq!(implied_by, cause.literals.len() - 1),
+      }
+    }
+    assert_eq!(graph.conflict.len(), conflict.literals.len());
+    assert!(graph
+      .conflict
+      .iter()
+      .all(|&lit| lit.assn(&solver.assignments) == Some(true)));
+  }
+  #[test]
+  fn backtrack_level() {
+    let mut solver = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf").unwrap();
+    let decide = |solver: &mut Solver| {
+      let lit = solver.choose_lit().unwrap();
+      assert_eq!(solver.with(lit, None), None);
+      !lit
+    };
+    solver.next_level();
+    let (a, b) = (decide(&mut solver), decide(&mut solver));
+    solver.next_level();
+    let c = decide(&mut solver);
+    assert_eq!(solver.backtrack_level(&[a, c]), 1);
+    assert_eq!(solver.backtrack_level(&[c, a, b]), 1);
+    // with a single level the second highest is the highest
+    assert_eq!(solver.backtrack_level(&[a, b]), 1);
+    assert_eq!(solver.backtrack_level(&[c]), 0);
+  }
+  #[test]
+  fn unsat_core_clauses() {
+    let path = "src/bin/data/small/aim-100-1_6-no.cnf";
+    let mut solver = Solver::from_dimacs(path).unwrap();
+    solver.track_unsat_core();
+    assert!(solver.unsat_core_clauses().is_empty());
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    let core = solver.unsat_core_clauses();
+    assert!(!core.is_empty());
+    assert!(core.len() < solver.db.initial().len());
+    let to_dimacs = |lit: &Literal| (lit.var() as i32 + 1) * if lit.negated() { -1 } else { 1 };
+    let clauses = core
+      .iter()
+      .map(|clause| clause.literals.iter().map(to_dimacs).collect())
+      .collect();
+    let mut core_solver = Solver::from_clauses(clauses, solver.num_vars());
+    assert_eq!(core_solver.solve(), SolveResult::Unsat);
+
+    let mut solver = Solver::from_dimacs("src/bin/data/small/sample_no.cnf").unwrap();
+    solver.track_unsat_core();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert!(!solver.unsat_core_clauses().is_empty());
+  }
+  #[test]
+  fn check_learnts() {
+    let mut solver = SolverBuilder::new()
+      .check_learnts(true)
+      .build_from_dimacs("src/bin/data/small/aim-100-1_6-no.cnf")
+      .unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert!(solver.stats.clauses_learned > 0);
+  }
+  #[test]
+  fn learnt_averages() {
+    let mut solver = Solver::from_dimacs("src/bin/data/med_unsat/pret60_25.cnf").unwrap();
+    assert_eq!(solver.stats.avg_learnt_len(), None);
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    let (len, lbd) = (
+      solver.stats.avg_learnt_len().unwrap(),
+      solver.stats.avg_lbd().unwrap(),
+    );
+    assert!(solver.stats.learnt_clauses > 0);
+    assert!(1.0 <= lbd && lbd <= len);
+  }
+  #[test]
+  fn max_restarts() {
+    let path = "src/bin/data/med_unsat/pret150_25.cnf";
+    let mut solver = Solver::from_dimacs(path).unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert!(solver.num_restarts() > 1);
+    for max in 0..2 {
+      let mut solver = SolverBuilder::new()
+        .max_restarts(Some(max))
+        .build_from_dimacs(path)
+        .unwrap();
+      assert_eq!(solver.solve(), SolveResult::Unsat);
+      assert_eq!(solver.num_restarts(), max);
+    }
+  }
+  #[test]
+  fn clause_activity() {
+    for (bump, decay) in [(1, 1.0), (CLAUSE_BUMP, CLAUSE_DECAY), (1, 0.0)] {
+      let mut solver = SolverBuilder::new()
+        .clause_bump(bump)
+        .clause_decay(decay)
+        .build_from_dimacs("src/bin/data/med_unsat/pret60_25.cnf")
+        .unwrap();
+      assert_eq!(solver.solve(), SolveResult::Unsat);
+    }
+  }
+  #[test]
+  fn no_sharing() {
+    use std::thread;
+    let solvers = SolverBuilder::new()
+      .share_clauses(false)
+      .build_from_dimacs("src/bin/data/med_unsat/pret150_25.cnf")
+      .unwrap()
+      .replicate(2)
+      .unwrap();
+    let db = solvers[0].db.clone();
+    let threads = solvers
+      .into_iter()
+      .map(|mut solver| thread::spawn(move || (solver.solve(), solver.stats)))
+      .collect::<Vec<_>>();
+    for thread in threads {
+      let (result, stats) = thread.join().unwrap();
+      assert_eq!(result, SolveResult::Unsat);
+      assert_eq!((stats.written_clauses, stats.transferred_clauses), (0, 0));
+    }
+    assert_eq!(db.learnt_iter().count(), 0);
+  }
+  #[test]
+  fn max_learnt_len() {
+    let builder = SolverBuilder::new().max_learnt_len(Some(3));
+    let mut solver = builder
+      .build_from_dimacs("src/bin/data/med_unsat/pret150_25.cnf")
+      .unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert!(solver.stats.learnt_literals > 3 * solver.stats.learnt_clauses);
+    // longer clauses are neither watched nor stored in the database
+    let watched = solver
+      .watch_list
+      .occurrences
+      .iter()
+      .flat_map(|watches| watches.keys())
+      .filter(|clause| !clause.initial)
+      .map(|clause| clause.literals.len())
+      .collect::<Vec<_>>();
+    assert!(!watched.is_empty());
+    assert!(watched.iter().all(|&len| len <= 3));
+    assert!(solver
+      .db
+      .learnt_iter()
+      .all(|clause| clause.literals.len() <= 3));
+
+    let mut solver = builder
+      .build_from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf")
+      .unwrap();
+    let model = solver.solve().model().unwrap();
+    assert!(solver.verify(&model));
+  }
+  #[test]
+  fn phase_hints() {
+    let path = "src/bin/data/small/aim-50-1_6-yes.cnf";
+    let mut solver = Solver::from_dimacs(path).unwrap();
+    let model = match solver.solve() {
+      SolveResult::Sat(model) => model,
+      result => panic!("expected SAT, got {:?}", result),
+    };
+    // hinting a model lets the solver decide straight into it without any conflicts
+    let mut hinted = Solver::from_dimacs(path).unwrap();
+    hinted.set_phases(&model.iter().map(|&val| Some(val)).collect::<Vec<_>>());
+    assert_eq!(hinted.solve(), SolveResult::Sat(model.clone()));
+    assert_eq!(hinted.stats.clauses_learned, 0);
+
+    let mut hinted = Solver::from_dimacs(path).unwrap();
+    hinted.set_phase_hint(0, !model[0]);
+    assert_eq!(hinted.polarities[0], !model[0]);
+    match hinted.solve() {
+      SolveResult::Sat(model) => assert!(hinted.verify(&model)),
+      result => panic!("expected SAT, got {:?}", result),
+    }
+  }
+  #[test]
+  fn no_learning() {
+    let path = "src/bin/data/small/aim-100-1_6-no.cnf";
+    let mut learning = Solver::from_dimacs(path).unwrap();
+    assert_eq!(learning.solve(), SolveResult::Unsat);
+    let mut solver = SolverBuilder::new()
+      .learning(false)
+      .build_from_dimacs(path)
+      .unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+    assert_eq!(solver.stats.restarts, 0);
+    assert_eq!(solver.db.learnt_iter().count(), 0);
+    // learnt clauses prune the rest of the search, so far fewer conflicts are needed with them
+    assert!(
+      solver.stats.clauses_learned > learning.stats.clauses_learned,
+      "{} conflicts without learning, {} with",
+      solver.stats.clauses_learned,
+      learning.stats.clauses_learned
+    );
+
+    let mut solver = SolverBuilder::new()
+      .learning(false)
+      .build_from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf")
+      .unwrap();
+    match solver.solve() {
+      SolveResult::Sat(model) => assert!(solver.verify(&model)),
+      result => panic!("expected SAT, got {:?}", result),
+    }
+  }
+  #[test]
+  fn rephase() {
+    let builder = SolverBuilder::new()
+      .rephase_interval(Some(2))
+      .rephase_policy(&[Phase::True, Phase::False]);
+    let mut solver = builder
+      .build_from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf")
+      .unwrap();
+    for (restarts, expected) in [(1, false), (2, true), (3, true), (4, false)] {
+      solver.stats.restarts = restarts;
+      solver.maybe_rephase();
+      assert!(solver.polarities.iter().all(|&p| p == expected));
+    }
+    let mut solver = builder
+      .rephase_interval(Some(1))
+      .build_from_dimacs("src/bin/data/med_unsat/pret60_25.cnf")
+      .unwrap();
+    assert_eq!(solver.solve(), SolveResult::Unsat);
+  }
+  #[test]
+  fn diverse_replicas() {
+    let configs = [
+      SolverConfig::default(),
+      SolverConfig {
+        restart_base: 50,
+        var_decay: 1.05,
+        phase: Phase::True,
+        ..SolverConfig::default()
+      },
+    ];
+    let mut solvers = Solver::from_dimacs("src/bin/data/small/aim-50-1_6-yes.cnf")
+      .unwrap()
+      .replicate_diverse(3, &configs)
+      .unwrap();
+    assert_eq!(solvers[0].restart_state, RestartState::new(100, 2));
+    assert_eq!(solvers[1].restart_state, RestartState::new(50, 2));
+    assert!(solvers[1].polarities.iter().all(|&p| p));
+    assert_eq!(
+      solvers[2].var_state.decay_rate,
+      solvers[0].var_state.decay_rate
+    );
+    let sol = solvers[1].solve().model().unwrap();
+    assert!(solvers[1].verify(&sol));
+  }
+  #[test]
+  fn deterministic() {
+    use std::thread;
+    let run = || {
+      let configs = (0..4)
+        .map(|i| SolverConfig {
+          seed: i,
+          phase: Phase::Random,
+          restart_base: 50 + 30 * i,
+          deterministic: Some(20),
+          ..SolverConfig::default()
+        })
+        .collect::<Vec<_>>();
+      let threads = Solver::from_dimacs("src/bin/data/jnh/jnh1.cnf")
+        .unwrap()
+        .replicate_diverse(4, &configs)
+        .unwrap()
+        .into_iter()
+        .map(|mut solver| thread::spawn(move || (solver.solve(), solver.found_by_self())))
+        .collect::<Vec<_>>();
+      let (results, found): (Vec<_>, Vec<_>) = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .unzip();
+      // every solver returns the winning model, and only the winner found it
+      assert!(results.iter().all(|result| *result == results[0]));
+      assert_eq!(found.iter().filter(|&&found| found).count(), 1);
+      (results[0].clone().model().unwrap(), found)
+    };
+    let model = run();
+    for _ in 0..3 {
+      assert_eq!(run(), model);
+    }
+  }
+}
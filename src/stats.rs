@@ -1,4 +1,8 @@
-use std::time::{Duration, Instant};
+use crate::solver::SolveResult;
+use std::{
+  fmt,
+  time::{Duration, Instant},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Stats {
@@ -15,6 +19,17 @@ pub struct Stats {
 
   /// For all the learned clauses, how many literals were there
   pub learnt_literals: usize,
+  /// how many learnt clauses were recorded with their literals and LBD
+  pub learnt_clauses: usize,
+  /// For all the learned clauses, the sum of their literal block distances, the number of
+  /// distinct decision levels among their literals when learnt
+  pub learnt_lbd: usize,
+
+  /// how many initial clauses were removed by subsumption
+  pub subsumed_clauses: usize,
+
+  /// the deepest decision level reached
+  max_level: usize,
 
   /// The start time of this solver
   pub start_time: Instant,
@@ -27,7 +42,15 @@ pub enum Record {
   Propogation,
   Written(u32),
   Transferred(usize),
-  LearntLiterals(usize),
+  /// A learnt clause with the given number of literals and LBD
+  LearntLiterals(usize, usize),
+  Subsumed(usize),
+  /// A new decision level was started
+  Level(usize),
+}
+
+impl Default for Stats {
+  fn default() -> Self { Self::new() }
 }
 
 impl Stats {
@@ -39,9 +62,16 @@ impl Stats {
       written_clauses: 0,
       transferred_clauses: 0,
       learnt_literals: 0,
+      learnt_clauses: 0,
+      learnt_lbd: 0,
+      subsumed_clauses: 0,
+      max_level: 0,
       start_time: Instant::now(),
     }
   }
+  /// Zeroes every counter and restarts the clock, so that the statistics only cover what
+  /// happens from now on
+  pub fn reset(&mut self) { *self = Self::new(); }
   #[inline]
   pub fn record(&mut self, rec: Record) {
     match rec {
@@ -50,9 +80,41 @@ impl Stats {
       Record::Propogation => self.propogations += 1,
       Record::Written(n) => self.written_clauses += n,
       Record::Transferred(n) => self.transferred_clauses += n,
-      Record::LearntLiterals(n) => self.learnt_literals += n,
+      Record::LearntLiterals(n, lbd) => {
+        self.learnt_clauses += 1;
+        self.learnt_literals += n;
+        self.learnt_lbd += lbd;
+      },
+      Record::Subsumed(n) => self.subsumed_clauses += n,
+      Record::Level(lvl) => self.max_level = self.max_level.max(lvl),
     };
   }
+  /// Returns the average number of literals in learnt clauses, or none if none were learnt
+  pub fn avg_learnt_len(&self) -> Option<f64> { self.average(self.learnt_literals) }
+  /// Returns the average LBD of learnt clauses, or none if none were learnt
+  pub fn avg_lbd(&self) -> Option<f64> { self.average(self.learnt_lbd) }
+  /// Returns the deepest decision level reached, where deep levels are a sign of a hard
+  /// instance or too few restarts
+  pub fn max_level(&self) -> usize { self.max_level }
+  fn average(&self, sum: usize) -> Option<f64> {
+    (self.learnt_clauses > 0).then(|| sum as f64 / self.learnt_clauses as f64)
+  }
+  /// Copies the counters and the time elapsed since the start into plain data
+  pub fn snapshot(&self) -> StatsSnapshot {
+    StatsSnapshot {
+      restarts: self.restarts,
+      clauses_learned: self.clauses_learned,
+      propagations: self.propogations,
+      written_clauses: self.written_clauses,
+      transferred_clauses: self.transferred_clauses,
+      learnt_literals: self.learnt_literals,
+      learnt_clauses: self.learnt_clauses,
+      learnt_lbd: self.learnt_lbd,
+      subsumed_clauses: self.subsumed_clauses,
+      max_level: self.max_level,
+      elapsed: self.start_time.elapsed(),
+    }
+  }
   /// Prints the rate for this solver given some unit time
   pub fn rate(&self, unit_time: Duration) {
     let total_time = self.start_time.elapsed();
@@ -69,9 +131,16 @@ impl Stats {
       "Propogations: {} ({}/{:?})",
       self.propogations, prop_rate as u32, unit_time
     );
+    if let (Some(len), Some(lbd)) = (self.avg_learnt_len(), self.avg_lbd()) {
+      println!(
+        "Learnt clauses {} (avg length {:.2}, avg LBD {:.2})",
+        self.learnt_clauses, len, lbd
+      );
+    }
+    println!("Max decision level {}", self.max_level);
     println!("Total time: {:?}", total_time);
   }
-  pub fn csv<S: AsRef<str>>(&self, name: S, num_cores: usize, sat: bool) {
+  pub fn csv<S: AsRef<str>>(&self, name: S, num_cores: usize, result: &SolveResult) {
     println!(
       "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
       name.as_ref(),
@@ -83,7 +152,50 @@ impl Stats {
       self.learnt_literals,
       self.start_time.elapsed().as_nanos(),
       num_cores,
-      if sat { "SAT" } else { "UNSAT" }
+      match result {
+        SolveResult::Sat(_) => "SAT",
+        SolveResult::Unsat => "UNSAT",
+        SolveResult::Unknown => "UNKNOWN",
+      }
+    )
+  }
+}
+
+/// A copy of the statistics of a solver at some point, see `Solver::statistics`.
+/// Unlike `Stats` this only holds plain data, so it can be stored, compared, and logged, and
+/// stays the same as `Stats` changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+  /// number of restarts
+  pub restarts: u32,
+  /// number of clauses learnt, one per conflict
+  pub clauses_learned: usize,
+  /// number of literals propagated
+  pub propagations: u32,
+  /// number of clauses written to the database
+  pub written_clauses: u32,
+  /// number of clauses transferred from other solvers
+  pub transferred_clauses: usize,
+  /// total number of literals in learnt clauses
+  pub learnt_literals: usize,
+  /// number of learnt clauses recorded with their literals and LBD
+  pub learnt_clauses: usize,
+  /// total LBD of learnt clauses
+  pub learnt_lbd: usize,
+  /// number of initial clauses removed by subsumption
+  pub subsumed_clauses: usize,
+  /// deepest decision level reached
+  pub max_level: usize,
+  /// time since the solver was created or its statistics were reset
+  pub elapsed: Duration,
+}
+
+impl fmt::Display for StatsSnapshot {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "restarts {}, conflicts {}, propagations {}, max level {}, elapsed {:?}",
+      self.restarts, self.clauses_learned, self.propagations, self.max_level, self.elapsed
     )
   }
 }
@@ -1,24 +1,38 @@
 extern crate priority_queue;
 
-use crate::{clause::Clause, database::ClauseDatabase};
-use ahash::ABuildHasher;
-use hashbrown::HashMap;
+use crate::{clause::Clause, database::ClauseDatabase, FixedState, HashMap};
 use priority_queue::PriorityQueue;
+use std::sync::Arc;
 
-#[derive(PartialOrd, Debug, PartialEq, Clone, Copy)]
-struct Priority(f32);
+/// The activity of a variable along with the variable itself, so that variables with equal
+/// activity are ordered by index, lowest first, instead of by where they are in the queue.
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Priority(f32, usize);
 
 impl Eq for Priority {}
+impl PartialOrd for Priority {
+  fn partial_cmp(&self, o: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(o)) }
+}
 impl Ord for Priority {
-  fn cmp(&self, o: &Self) -> std::cmp::Ordering { self.partial_cmp(o).unwrap() }
+  fn cmp(&self, o: &Self) -> std::cmp::Ordering {
+    self
+      .0
+      .partial_cmp(&o.0)
+      .unwrap()
+      .then_with(|| o.1.cmp(&self.1))
+  }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct VariableState {
   // Variable -> activity
-  priorities: PriorityQueue<usize, Priority, ABuildHasher>,
+  // Shared between clones until one of them changes it, so replicating a solver does not copy
+  // the whole queue up front
+  priorities: Arc<PriorityQueue<usize, Priority, FixedState>>,
   /// buffer for assigned variables
   evicted: HashMap<usize, Priority>,
+  /// variables which were unassigned but not yet put back into the queue
+  unassigned: Vec<usize>,
   /// constant rate of decay for this state
   pub decay_rate: f32,
 
@@ -33,8 +47,7 @@ impl VariableState {
   /// decays the current occurrence account
   pub fn decay(&mut self) {
     let decay_rate = self.decay_rate;
-    self
-      .priorities
+    Arc::make_mut(&mut self.priorities)
       .iter_mut()
       .for_each(|(_, v)| v.0 /= decay_rate);
     self.evicted.values_mut().for_each(|v| v.0 /= decay_rate);
@@ -46,9 +59,8 @@ impl VariableState {
       assert!(prio.0.is_sign_positive());
       prio.0 += inc_amt
     } else {
-      self
-        .priorities
-        .change_priority_by(&var, |p| Priority(p.0 + inc_amt));
+      Arc::make_mut(&mut self.priorities)
+        .change_priority_by(&var, |p| Priority(p.0 + inc_amt, p.1));
     }
   }
   /// Adds a clause to this variable state cache
@@ -59,30 +71,112 @@ impl VariableState {
   }
   pub fn enable(&mut self, var: usize) {
     if let Some(prev) = self.evicted.remove(&var) {
-      self.priorities.push(var, prev);
+      Arc::make_mut(&mut self.priorities).push(var, prev);
+    }
+  }
+  /// Marks a variable as unassigned, deferring putting it back into the queue until the next
+  /// decision, see `enable_unassigned`.
+  pub fn enable_lazily(&mut self, var: usize) { self.unassigned.push(var); }
+  /// Puts variables marked as unassigned back into the queue if they are still unassigned.
+  /// Those which were assigned again since stay out of it, and are marked again whenever they
+  /// are next unassigned, so that every unassigned variable can still be decided.
+  pub fn enable_unassigned(&mut self, assignments: &[Option<bool>]) {
+    let mut unassigned = std::mem::take(&mut self.unassigned);
+    unassigned
+      .drain(..)
+      .filter(|&var| assignments[var].is_none())
+      .for_each(|var| self.enable(var));
+    self.unassigned = unassigned;
+  }
+  /// Permanently removes a variable, so that it will never be picked.
+  pub fn remove(&mut self, var: usize) {
+    // the queue has no removal, so move the variable to the top and pop it instead
+    let priorities = Arc::make_mut(&mut self.priorities);
+    if priorities
+      .change_priority(&var, Priority(f32::INFINITY, var))
+      .is_some()
+    {
+      priorities.pop();
     }
+    self.evicted.remove(&var);
   }
-  /// returns the variable with highest priority
+  /// Returns the activity of var, or 0 if it was removed
+  pub fn activity(&self, var: usize) -> f32 {
+    self
+      .evicted
+      .get(&var)
+      .or_else(|| self.priorities.get_priority(&var))
+      .map_or(0.0, |prio| prio.0)
+  }
+  /// Sets the activity of var, unless it was removed. Activities are clamped to be finite and
+  /// non-negative, since removing a variable relies on nothing else being infinitely active.
+  pub fn set_activity(&mut self, var: usize, activity: f32) {
+    let activity = if activity.is_nan() {
+      0.0
+    } else {
+      activity.clamp(0.0, f32::MAX)
+    };
+    if let Some(prio) = self.evicted.get_mut(&var) {
+      prio.0 = activity;
+    } else {
+      Arc::make_mut(&mut self.priorities).change_priority(&var, Priority(activity, var));
+    }
+  }
+  /// returns the variable with highest priority, or none if every variable was taken
   /// Modifies the internal state so that the variable cannot be picked again
   /// Until it is re-enabled
-  pub fn take_highest_prio(&mut self) -> usize {
-    let next = self.priorities.pop().unwrap();
+  pub fn take_highest_prio(&mut self) -> Option<usize> {
+    let next = Arc::make_mut(&mut self.priorities).pop()?;
     self.evicted.insert(next.0, next.1);
-    next.0
+    Some(next.0)
   }
 }
 
 impl From<&'_ ClauseDatabase> for VariableState {
   fn from(db: &ClauseDatabase) -> Self {
     let mut priorities = PriorityQueue::with_capacity_and_default_hasher(db.max_var);
-    priorities.extend((0..db.max_var).map(|var| (var, Priority(0.0))));
+    priorities.extend((0..db.max_var).map(|var| (var, Priority(0.0, var))));
     let mut state = Self {
-      priorities,
-      evicted: HashMap::new(),
+      priorities: Arc::new(priorities),
+      evicted: HashMap::default(),
+      unassigned: vec![],
       decay_rate: DEFAULT_DECAY_RATE,
       inc_amt: DEFAULT_INC_AMT,
     };
-    db.iter().for_each(|cref| state.add_clause(&cref));
+    db.initial_iter().for_each(|cref| state.add_clause(&cref));
     state
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::literal::Literal;
+  #[test]
+  fn ties_broken_by_index() {
+    let clauses = vec![
+      Clause::from(vec![Literal::from(3), Literal::from(-2)]),
+      Clause::from(vec![Literal::from(-4), Literal::from(1)]),
+    ];
+    let mut state = VariableState::from(&ClauseDatabase::new(5, clauses));
+    let order = std::iter::from_fn(|| state.take_highest_prio()).collect::<Vec<_>>();
+    assert_eq!(order, vec![0, 1, 2, 3, 4]);
+  }
+  #[test]
+  fn set_activity() {
+    let clauses = vec![Clause::from(vec![Literal::from(1), Literal::from(2)])];
+    let mut state = VariableState::from(&ClauseDatabase::new(4, clauses));
+    state.remove(3);
+    state.set_activity(3, 5.0);
+    state.set_activity(2, f32::INFINITY);
+    state.set_activity(1, f32::NAN);
+    assert_eq!(state.take_highest_prio(), Some(2));
+    // assigned variables keep their new activity when put back
+    state.set_activity(2, -1.0);
+    state.enable(2);
+    assert_eq!(state.activity(2), 0.0);
+    assert_eq!(state.activity(3), 0.0);
+    let order = std::iter::from_fn(|| state.take_highest_prio()).collect::<Vec<_>>();
+    assert_eq!(order, vec![0, 1, 2]);
+  }
+}
@@ -1,8 +1,11 @@
 use crate::{
   database::{ClauseDatabase, ClauseRef},
   literal::Literal,
+  solver::WatchSelection,
+  var_state::VariableState,
+  HashMap, HashSet,
 };
-use hashbrown::{hash_map::Entry, HashMap};
+use hashbrown::hash_map::Entry;
 use std::sync::{
   atomic::{AtomicU64, Ordering},
   Arc, Weak,
@@ -12,43 +15,88 @@ use std::sync::{
 #[derive(Clone, Debug)]
 pub struct WatchList {
   // raw literal ->  Vec(Clause being watched, other literal being watched in clause)
-  occurrences: Vec<HashMap<ClauseRef, Literal>>,
+  // These use the crate's fixed ahash state like every other map, and clauses hash a few of
+  // their literals rather than their address so that iteration order is reproducible.
+  pub(crate) occurrences: Vec<HashMap<ClauseRef, Literal>>,
   // activities for the clauses in this watchlist
   activities: Vec<Weak<AtomicU64>>,
 }
 
 /// leaves enough space for both true and false variables up to max_var.
+/// Panics if there are too many variables to index every literal.
 #[inline]
-fn space_for_all_lits(size: usize) -> usize { (size << 1) }
+fn space_for_all_lits(size: usize) -> usize {
+  size
+    .checked_mul(2)
+    // literals store their variable in the upper 31 bits of a u32
+    .filter(|_| size <= 1 << 31)
+    .unwrap_or_else(|| panic!("{} variables is too many to represent every literal", size))
+}
 
 impl WatchList {
   /// returns a new watchlist, as well as any unit clauses
   /// from the initial constraints
-  pub fn new(db: &ClauseDatabase) -> (Self, Vec<(ClauseRef, Literal)>) {
+  pub fn new(
+    db: &ClauseDatabase,
+    selection: WatchSelection,
+    var_state: &VariableState,
+  ) -> (Self, Vec<(ClauseRef, Literal)>) {
+    Self::watching(db, db.initial_iter(), selection, var_state)
+  }
+  /// returns a new watchlist of the given clauses from db, as well as any unit clauses among
+  /// them. Each clause watches the literals picked by selection, using the activities in
+  /// var_state if needed.
+  pub fn watching(
+    db: &ClauseDatabase,
+    clauses: impl Iterator<Item = ClauseRef>,
+    selection: WatchSelection,
+    var_state: &VariableState,
+  ) -> (Self, Vec<(ClauseRef, Literal)>) {
     let mut wl = Self {
-      occurrences: vec![HashMap::new(); space_for_all_lits(db.max_var)],
+      occurrences: vec![HashMap::default(); space_for_all_lits(db.max_var)],
       activities: vec![],
     };
-    let units = db
-      .iter()
-      .filter_map(|cref| wl.watch(&cref).map(|lit| (cref, lit)))
+    let units = clauses
+      .filter_map(|cref| wl.watch(&cref, selection, var_state).map(|lit| (cref, lit)))
       .collect();
     (wl, units)
   }
   /// Adds some clause from the given database to this list.
   /// It must not have previously been added to the list.
-  fn watch(&mut self, cref: &ClauseRef) -> Option<Literal> {
-    let mut lits = cref.literals.iter().take(2);
-    match lits.next() {
-      None => panic!("Empty clause passed to watch"),
-      Some(&lit) => match lits.next() {
-        None => Some(lit),
-        Some(&o_lit) => {
-          assert!(self.add_clause_with_lits(cref.clone(), lit, o_lit));
-          None
-        },
+  fn watch(
+    &mut self,
+    cref: &ClauseRef,
+    selection: WatchSelection,
+    var_state: &VariableState,
+  ) -> Option<Literal> {
+    let mut lits = cref.literals.iter().copied();
+    let (lit, o_lit) = match (lits.next(), lits.next()) {
+      (None, _) => panic!("Empty clause passed to watch"),
+      (Some(lit), None) => return Some(lit),
+      (Some(lit), Some(o_lit)) => (lit, o_lit),
+    };
+    let (lit, o_lit) = match selection {
+      WatchSelection::First => (lit, o_lit),
+      WatchSelection::LowestActivity => {
+        let activity = |lit: Literal| var_state.activity(lit.var());
+        // ties keep the earlier literal, so the choice does not depend on float noise
+        let mut lowest = if activity(o_lit) < activity(lit) {
+          (o_lit, lit)
+        } else {
+          (lit, o_lit)
+        };
+        for lit in lits {
+          if activity(lit) < activity(lowest.0) {
+            lowest = (lit, lowest.0);
+          } else if activity(lit) < activity(lowest.1) {
+            lowest.1 = lit;
+          }
+        }
+        lowest
       },
-    }
+    };
+    assert!(self.add_clause_with_lits(cref.clone(), lit, o_lit));
+    None
   }
   /// adds a learnt clause, which is assumed to have at least two literals as well as cause
   /// and implication.
@@ -72,12 +120,12 @@ impl WatchList {
     let false_lit = *cref
       .literals
       .iter()
-      .find(|lit| lit.assn(&assns) == Some(false))
+      .find(|lit| lit.assn(assns) == Some(false))
       .unwrap();
     let unassn = *cref
       .literals
       .iter()
-      .find(|lit| lit.assn(&assns).is_none())
+      .find(|lit| lit.assn(assns).is_none())
       .unwrap();
     if let Entry::Vacant(v) = self.occurrences[unassn.raw() as usize].entry(cref.clone()) {
       v.insert(false_lit);
@@ -87,6 +135,25 @@ impl WatchList {
     }
     unassn
   }
+  /// returns the number of clauses currently watching lit
+  pub fn watch_count(&self, lit: Literal) -> usize { self.occurrences[lit.raw() as usize].len() }
+  /// returns the number of watches on each variable, across both of its literals
+  pub fn occurrence_histogram(&self) -> Vec<usize> {
+    self
+      .occurrences
+      .chunks(2)
+      .map(|lits| lits.iter().map(|watches| watches.len()).sum())
+      .collect()
+  }
+  /// returns each learnt clause currently watched, once
+  pub(crate) fn learnt_clauses(&self) -> HashSet<&ClauseRef> {
+    self
+      .occurrences
+      .iter()
+      .flat_map(|watches| watches.keys())
+      .filter(|cref| !cref.initial)
+      .collect()
+  }
   pub fn set<T>(&mut self, lit: Literal, assns: &[Option<bool>], into: &mut T)
   where
     T: Extend<(ClauseRef, Literal)>, {
@@ -99,20 +166,20 @@ impl WatchList {
   where
     T: Extend<(ClauseRef, Literal)>, {
     use std::mem::swap;
-    let mut swap_map = HashMap::new();
+    let mut swap_map = HashMap::default();
     swap(&mut self.occurrences[lit.raw() as usize], &mut swap_map);
     // removing items from the list without draining
     // should help improve efficiency
     swap_map.retain(|cref, &mut o_lit| {
-      assert_ne!(lit, o_lit);
+      debug_assert_ne!(lit, o_lit);
       // If the other one is set to true, we shouldn't update the watch list
       if o_lit.assn(assns) == Some(true) {
-        debug_assert_eq!(self.occurrences[o_lit.raw() as usize][&cref], lit);
+        debug_assert_eq!(self.occurrences[o_lit.raw() as usize][cref], lit);
         return true;
       }
       let mut next = None;
-      let mut lits = cref.literals.iter().filter(|&&lit| lit != o_lit);
-      while let Some(lit) = lits.next() {
+      let lits = cref.literals.iter().filter(|&&lit| lit != o_lit);
+      for lit in lits {
         match lit.assn(assns) {
           Some(false) => (),
           None => {
@@ -128,7 +195,11 @@ impl WatchList {
         // In the case of none, then it implies this is a unit clause,
         // so return it and the literal that needs to be set in it.
         None => {
-          debug_assert_eq!(self.occurrences[o_lit.raw() as usize][&cref], lit);
+          #[cfg(feature = "clause-stats")]
+          if o_lit.assn(assns).is_none() {
+            cref.record_propagation();
+          }
+          debug_assert_eq!(self.occurrences[o_lit.raw() as usize][cref], lit);
           into.extend(std::iter::once((cref.clone(), o_lit)));
           true
         },
@@ -136,11 +207,11 @@ impl WatchList {
           debug_assert_ne!(lit, next);
           debug_assert_ne!(o_lit, next);
           *self.occurrences[o_lit.raw() as usize]
-            .get_mut(&cref)
+            .get_mut(cref)
             .unwrap() = next;
           self.occurrences[next.raw() as usize].insert(cref.clone(), o_lit);
-          debug_assert_eq!(self.occurrences[next.raw() as usize][&cref], o_lit);
-          debug_assert_eq!(self.occurrences[o_lit.raw() as usize][&cref], next);
+          debug_assert_eq!(self.occurrences[next.raw() as usize][cref], o_lit);
+          debug_assert_eq!(self.occurrences[o_lit.raw() as usize][cref], next);
           debug_assert!(next.assn(assns) != Some(false));
           false
         },
@@ -152,6 +223,7 @@ impl WatchList {
   /// If all literals are false
   /// - And none have causes => Pick one at random(Maybe one with lowest priority)
   /// - And some have causes => Pick one with highest level
+  ///
   /// Else if one literal is true, watch true lit and any false
   /// Else if one literal is unassigned, watch it and any false and return it
   /// Else watch unassigneds.
@@ -173,9 +245,7 @@ impl WatchList {
     if self.already_exists(cref) {
       return None;
     }
-    let mut watchable = literals
-      .iter()
-      .filter(|lit| lit.assn(&assns) != Some(false));
+    let mut watchable = literals.iter().filter(|lit| lit.assn(assns) != Some(false));
     match watchable.next() {
       None => {
         // this case can cause unsoundness on some rare occasions
@@ -200,10 +270,8 @@ impl WatchList {
           Some(true) => None,
           Some(false) => unreachable!(),
           None => {
-            if !self.occurrences[lit.raw() as usize].contains_key(&cref) {
-              let other = *literals
-                .iter()
-                .find(|lit| lit.assn(&assns) == Some(false))?;
+            if !self.occurrences[lit.raw() as usize].contains_key(cref) {
+              let other = *literals.iter().find(|lit| lit.assn(assns) == Some(false))?;
               self.activities.push(Arc::downgrade(&cref.activity));
               assert!(self.add_clause_with_lits(cref.clone(), lit, other));
             }
@@ -237,7 +305,10 @@ impl WatchList {
         .is_none()
   }
 
-  pub fn remove_satisfied(&mut self, assns: &[Option<bool>]) {
+  /// Stops watching clauses with a watched literal which is true, returning the learnt
+  /// clauses among them.
+  pub fn remove_satisfied(&mut self, assns: &[Option<bool>]) -> Vec<ClauseRef> {
+    let mut removed = HashSet::default();
     // TODO could I swap the ordering here of which lit is being removed
     self
       .occurrences
@@ -246,50 +317,66 @@ impl WatchList {
       .filter(|(_, watches)| !watches.is_empty())
       .for_each(|(lit, watches)| {
         if Literal::from(lit as u32).assn(assns) == Some(true) {
-          watches.clear();
+          removed.extend(watches.drain().map(|(cref, _)| cref).filter(|c| !c.initial));
         } else {
-          watches.retain(|_, other_lit| other_lit.assn(assns) != Some(true));
+          watches.retain(|cref, other_lit| {
+            let satisfied = other_lit.assn(assns) == Some(true);
+            if satisfied && !cref.initial {
+              removed.insert(cref.clone());
+            }
+            !satisfied
+          });
         }
         watches.shrink_to_fit();
       });
+    removed.into_iter().collect()
   }
-  pub fn clause_decay(&mut self) {
+  /// Multiplies the activity of every clause in this watchlist by decay, and forgets the
+  /// activities of clauses which were freed
+  pub fn clause_decay(&mut self, decay: f64) {
     self.activities.retain(|act| {
       act
         .upgrade()
-        .map(|act| {
+        .inspect(|act| {
           let mut data = act.load(Ordering::Relaxed);
           while let Err(v) = act.compare_exchange(
             data,
-            (data as f64 * 0.9) as u64,
+            (data as f64 * decay) as u64,
             Ordering::Relaxed,
             Ordering::Relaxed,
           ) {
             data = v;
           }
-          act
         })
         .is_some()
     });
   }
-  /// returns the median activity for this watchlist
-  fn median_activity(&mut self) -> Option<u64> {
-    let median_position = self.activities.len() / 2;
+  /// returns the activity above which the given fraction of clauses in this watchlist lie
+  fn activity_quantile(&mut self, keep: f64) -> Option<u64> {
+    let len = self.activities.len();
+    let position = ((len as f64) * (1.0 - keep)).clamp(0.0, (len - 1) as f64) as usize;
     self
       .activities
-      .partition_at_index_by_key(median_position, |act| {
+      .select_nth_unstable_by_key(position, |act| {
         act.upgrade().map_or(0, |act| act.load(Ordering::SeqCst))
       })
       .1
       .upgrade()
       .map(|act| act.load(Ordering::SeqCst))
   }
-  /// removes some old clauses from the databse
-  pub fn clean(&mut self, assns: &[Option<bool>], causes: &[Option<ClauseRef>]) {
+  /// removes some old clauses from the databse, keeping roughly the fraction keep of the most
+  /// active learnt clauses, then decays the activity of those remaining
+  pub fn clean(
+    &mut self,
+    assns: &[Option<bool>],
+    causes: &[Option<ClauseRef>],
+    keep: f64,
+    decay: f64,
+  ) {
     if self.activities.is_empty() {
       return;
     }
-    let threshold = match self.median_activity() {
+    let threshold = match self.activity_quantile(keep) {
       None => return,
       Some(med) => med,
     };
@@ -309,7 +396,10 @@ impl WatchList {
       .filter(|(_, watches)| !watches.is_empty())
       .for_each(|(lit, watches)| {
         let lit = Literal::from(lit as u32);
-        // Threshold is the median of all clause activities for this watch list
+        // Threshold is the quantile of all clause activities for this watch list.
+        // A clause which is the reason for a literal always watches that literal, since it was
+        // the unassigned watch when the clause became unit, and true watches are never moved.
+        // Both watches are checked, so reasons are kept no matter their activity.
         watches.retain(|cref, &mut o_lit| {
           cref.literals.len() <= 2
             || cref.initial
@@ -320,7 +410,149 @@ impl WatchList {
         watches.shrink_to_fit();
       });
     drop(curr);
-    self.activities.retain(|act| act.strong_count() > 0);
+    self.clause_decay(decay);
     self.activities.shrink_to_fit();
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::clause::Clause;
+  use proptest::prelude::*;
+
+  const MAX_VAR: usize = 8;
+
+  /// Assigns lit along with everything it implies through the watch list, checking that every
+  /// clause reported is unit or conflicting under the assignments by brute force.
+  /// Returns false if a conflict was reported.
+  fn propagate(wl: &mut WatchList, assns: &mut [Option<bool>], lit: Literal) -> bool {
+    let mut queue = vec![lit];
+    assns[lit.var()] = Some(lit.val());
+    while let Some(lit) = queue.pop() {
+      let mut reported = vec![];
+      wl.set(lit, assns, &mut reported);
+      for (cref, o_lit) in reported {
+        assert!(cref
+          .literals
+          .iter()
+          .filter(|&&other| other != o_lit)
+          .all(|other| other.assn(assns) == Some(false)));
+        match o_lit.assn(assns) {
+          Some(true) => {},
+          Some(false) => return false,
+          None => {
+            assert_eq!(cref.unit_under(assns), Some(o_lit));
+            assns[o_lit.var()] = Some(o_lit.val());
+            queue.push(o_lit);
+          },
+        }
+      }
+    }
+    true
+  }
+
+  fn formula() -> impl Strategy<Value = Vec<Vec<i32>>> {
+    let lit = (1..=MAX_VAR as i32).prop_flat_map(|var| prop_oneof![Just(var), Just(-var)]);
+    prop::collection::vec(prop::collection::vec(lit, 2..5), 1..30)
+  }
+
+  #[test]
+  #[should_panic(expected = "too many")]
+  fn too_many_vars() { space_for_all_lits(usize::MAX); }
+
+  #[test]
+  fn lowest_activity_watches() {
+    let clause = Clause::from(vec![Literal::from(1), Literal::from(2), Literal::from(3)]);
+    let db = ClauseDatabase::new(3, vec![clause]);
+    let mut var_state = VariableState::from(&db);
+    var_state.increase_var_activity(0);
+    let (wl, _) = WatchList::new(&db, WatchSelection::First, &var_state);
+    assert_eq!(wl.watch_count(Literal::from(3)), 0);
+    let (wl, _) = WatchList::new(&db, WatchSelection::LowestActivity, &var_state);
+    assert_eq!(wl.watch_count(Literal::from(1)), 0);
+    assert_eq!(wl.watch_count(Literal::from(2)), 1);
+    assert_eq!(wl.watch_count(Literal::from(3)), 1);
+  }
+
+  #[test]
+  fn clean_keeps_reasons() {
+    let initial = Clause::from(vec![Literal::from(1), Literal::from(-9)]);
+    let db = ClauseDatabase::new(9, vec![initial]);
+    let (mut wl, _) = WatchList::new(&db, WatchSelection::First, &VariableState::from(&db));
+    // adds the clause of lits while every lit except the last is false
+    let mut learn = |lits: Vec<i32>| {
+      let cref = ClauseRef::from(Clause::from(
+        lits
+          .iter()
+          .map(|&lit| Literal::from(lit))
+          .collect::<Vec<_>>(),
+      ));
+      let mut assns = vec![None; 9];
+      for &lit in &lits[..lits.len() - 1] {
+        assns[Literal::from(lit).var()] = Some(lit < 0);
+      }
+      wl.add_learnt(&assns, &cref);
+      cref
+    };
+    let reason = learn(vec![1, 2, 3]);
+    learn(vec![4, 5, 6]);
+    let active = learn(vec![7, 8, 9]);
+    active.boost(10);
+    let mut assns = vec![None; 9];
+    assns[0] = Some(false);
+    assns[1] = Some(false);
+    assns[2] = Some(true);
+    let mut causes = vec![None; 9];
+    causes[2] = Some(reason.clone());
+    // only the most active clause is above the threshold
+    wl.clean(&assns, &causes, 0.1, 0.5);
+    assert_eq!(wl.watch_count(Literal::from(3)), 1);
+    assert_eq!(wl.watch_count(Literal::from(6)), 0);
+    assert_eq!(wl.watch_count(Literal::from(9)), 1);
+  }
+
+  proptest! {
+    #[test]
+    fn set_matches_brute_force(
+      clauses in formula(),
+      decisions in prop::collection::vec(any::<u32>(), 1..40),
+      lowest_activity in any::<bool>(),
+    ) {
+      let clauses = clauses
+        .into_iter()
+        .map(|lits| Clause::from(lits.into_iter().map(Literal::from).collect::<Vec<_>>()))
+        .filter(|clause| clause.literals.len() > 1)
+        .collect::<Vec<_>>();
+      prop_assume!(!clauses.is_empty());
+      let db = ClauseDatabase::new(MAX_VAR, clauses);
+      let selection = if lowest_activity {
+        WatchSelection::LowestActivity
+      } else {
+        WatchSelection::First
+      };
+      let (mut wl, units) = WatchList::new(&db, selection, &VariableState::from(&db));
+      assert!(units.is_empty());
+      let mut assns = vec![None; MAX_VAR];
+      for decision in decisions {
+        let lit = Literal::from(decision % (2 * MAX_VAR as u32));
+        if lit.assn(&assns).is_some() {
+          continue;
+        }
+        if !propagate(&mut wl, &mut assns, lit) {
+          // every watch must stay valid after backtracking, so keep going from scratch
+          assert!(db
+            .iter()
+            .any(|c| c.literals.iter().all(|l| l.assn(&assns) == Some(false))));
+          assns.iter_mut().for_each(|assn| *assn = None);
+          continue;
+        }
+        // at a fixpoint no clause is unit or falsified
+        for cref in db.iter() {
+          assert_eq!(cref.unit_under(&assns), None);
+          assert!(cref.literals.iter().any(|l| l.assn(&assns) != Some(false)));
+        }
+      }
+    }
+  }
+}